@@ -2,7 +2,8 @@ use crate::utils::geometry::{convert_map_to_grid, render_image};
 use crate::utils::geometry::{CardinalDirection, Vec2D};
 use crate::utils::read::read_list;
 use intcode_computer::prelude::*;
-use std::collections::{HashMap, VecDeque};
+use std::cell::RefCell;
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, Copy)]
 enum Color {
@@ -91,35 +92,28 @@ impl EmergencyPaintingRobot {
 }
 
 fn take_the_robot_for_a_walk(software: IntcodeMemoryType, hull: ShipHull) -> ShipHull {
-    let mut output_buffer: VecDeque<IntcodeMemoryCellType> = VecDeque::new();
     let mut computer = IntCodeComputer::new(software);
-    let mut robot = EmergencyPaintingRobot::new();
-    let mut hull = hull;
-
-    let current_color = hull.get_color(&robot.location);
-    computer.provide_input((*current_color).into());
-    execute! { computer,
-        output {
-            output_buffer.push_back(computer.take_output());
-        },
-        input {
-            assert_eq!(output_buffer.len(), 2);
-            let color_to_paint: Color = output_buffer.pop_front().unwrap().into();
-            let direction_to_turn: RotationDirection = match output_buffer.pop_front().unwrap()
-            {
-                0 => RotationDirection::Left,
-                1 => RotationDirection::Right,
-                _ => panic!("invalid direction to turn"),
-            };
-            hull.paint(robot.location, color_to_paint);
-            robot.rotate(direction_to_turn);
-            robot.advance();
-            // for next iter
-            let current_color = hull.get_color(&robot.location);
-            computer.provide_input((*current_color).into());
-        }
+    let robot = RefCell::new(EmergencyPaintingRobot::new());
+    let hull = RefCell::new(hull);
+
+    let camera = || {
+        let current_color = *hull.borrow_mut().get_color(&robot.borrow().location);
+        Some(current_color.into())
     };
-    hull
+    for instruction in computer.output_chunks(camera) {
+        let [color_to_paint, direction_to_turn] = instruction.expect("robot faulted");
+        let direction_to_turn = match direction_to_turn {
+            0 => RotationDirection::Left,
+            1 => RotationDirection::Right,
+            _ => panic!("invalid direction to turn"),
+        };
+        let mut robot = robot.borrow_mut();
+        hull.borrow_mut()
+            .paint(robot.location, color_to_paint.into());
+        robot.rotate(direction_to_turn);
+        robot.advance();
+    }
+    hull.into_inner()
 }
 
 fn get_test_input() -> IntcodeMemoryType {
@@ -1,6 +1,7 @@
 use crate::utils::geometry::{convert_map_to_grid, render_image, Vec2D};
 use crate::utils::read::read_list;
 use intcode_computer::prelude::*;
+use std::cell::RefCell;
 use std::collections::HashMap;
 
 #[derive(PartialEq, Eq, Clone, Copy)]
@@ -41,38 +42,33 @@ fn get_test_input() -> IntcodeMemoryType {
     read_list(include_str!("day13_input.txt"), ",")
 }
 
-fn run_game(game: IntcodeMemoryType) -> IntcodeMemoryType {
-    let mut outputs = Vec::new();
+fn run_game(game: IntcodeMemoryType) -> Vec<[i64; 3]> {
     let mut computer = IntCodeComputer::new(game);
-    execute! { computer,
-        output { outputs.push(computer.take_output()) }
-    }
-    outputs
+    let mut frames = computer.output_chunks(|| None);
+    let tiles = frames
+        .by_ref()
+        .collect::<Result<_, _>>()
+        .expect("game faulted");
+    assert!(frames.remainder().is_empty(), "incomplete tile");
+    tiles
 }
 
 pub fn count_blocks() -> usize {
     let input = get_test_input();
-    let mut computer_output = run_game(input);
-    let (_, map) = output_to_map(&mut computer_output);
+    let tiles = run_game(input);
+    let (_, map) = output_to_map(tiles);
     map.values()
         .cloned()
         .filter(|tile| *tile == Entity::Block)
         .count()
 }
 
-fn output_to_map(output: &mut IntcodeMemoryType) -> (i64, HashMap<Vec2D, Entity>) {
-    assert_eq!(output.len() % 3, 0);
+fn output_to_map(tiles: Vec<[i64; 3]>) -> (i64, HashMap<Vec2D, Entity>) {
     let mut score = 0;
     let mut grid = HashMap::new();
-    let elements: Vec<_> = output.drain(..).collect();
-    for tile in elements.chunks(3) {
-        if tile.len() < 3 {
-            output.extend(tile);
-            break;
-        }
-        let (x, y, tile_id) = (tile[0], tile[1], tile[2]);
+    for [x, y, tile_id] in tiles {
         if x == -1 && y == 0 {
-            score += tile_id;
+            score = tile_id;
             continue;
         }
         let point = Vec2D::new(x as i32, y as i32);
@@ -86,37 +82,36 @@ const PRINT_FRAMES: bool = false;
 pub fn play_game() -> i64 {
     let mut game = get_test_input();
     game[0] = 2;
-    let mut outputs = Vec::new();
     let mut computer = IntCodeComputer::new(game);
     let mut total_score = 0;
-    let mut screen = HashMap::new();
+    let screen = RefCell::new(HashMap::new());
 
-    execute! { computer,
-        output {
-            outputs.push(computer.take_output());
-            if outputs.len() % 3 == 0 {
-                let (score, mut map) = output_to_map(&mut outputs);
-                screen.extend(map.drain());
-                total_score = score;
-            }
-        },
-        input {
-            let convert_entity = |entity| Into::<i64>::into(entity) as i32;
-            let (width, grid) = convert_map_to_grid(&screen, 0, Box::new(convert_entity));
-            let ball = find_first(&grid, width, 4);
-            let puck = find_first(&grid, width, 3);
-            if ball.x == puck.x {
-                computer.provide_input(0);
-            } else if ball.x < puck.x {
-                computer.provide_input(-1);
-            } else {
-                computer.provide_input(1);
-            }
-            if PRINT_FRAMES {
-                let frame = render_image(grid, width, Box::new(render_pixel));
-                print!("{}", frame);
-                std::thread::sleep(std::time::Duration::from_millis(1000 / 60));
-            }
+    let joystick = || {
+        let convert_entity = |entity| Into::<i64>::into(entity) as i32;
+        let (width, grid) = convert_map_to_grid(&screen.borrow(), 0, Box::new(convert_entity));
+        let ball = find_first(&grid, width, 4);
+        let puck = find_first(&grid, width, 3);
+        let tilt = if ball.x == puck.x {
+            0
+        } else if ball.x < puck.x {
+            -1
+        } else {
+            1
+        };
+        if PRINT_FRAMES {
+            let frame = render_image(grid, width, Box::new(render_pixel));
+            print!("{}", frame);
+            std::thread::sleep(std::time::Duration::from_millis(1000 / 60));
+        }
+        Some(tilt)
+    };
+    for tile in computer.output_chunks(joystick) {
+        let [x, y, tile_id] = tile.expect("game faulted");
+        if x == -1 && y == 0 {
+            total_score = tile_id;
+        } else {
+            let point = Vec2D::new(x as i32, y as i32);
+            screen.borrow_mut().insert(point, Entity::from(tile_id));
         }
     }
     total_score
@@ -1,4 +1,5 @@
 use crate::utils::read::read_list;
+use intcode_computer::ascii::AsciiIo;
 use intcode_computer::prelude::*;
 use crate::utils::geometry::{CardinalDirection, Vec2D};
 use std::collections::HashSet;
@@ -8,15 +9,9 @@ fn get_test_input() -> IntcodeMemoryType {
 }
 
 fn get_ascii(program: IntcodeMemoryType) -> Vec<char> {
-    let mut computer = IntCodeComputer::new(program);
-    let mut out_buffer = Vec::<char>::new(); 
-    execute! {computer,
-        output { out_buffer.push(computer.take_output() as u8 as char) }
-    }
-    while let Some('\n') = out_buffer.last() {
-      out_buffer.pop();
-    }
-    out_buffer
+    let mut ascii = AsciiIo::new(IntCodeComputer::new(program));
+    let output = ascii.run().expect("camera program faulted");
+    output.lines.join("\n").trim_end_matches('\n').chars().collect()
 }
 
 struct View {
@@ -32,7 +32,7 @@ mod tests {
     #[test]
     fn test_execute_empty() {
         let mut computer = IntCodeComputer::new(vec![]);
-        let interrupted = computer.execute();
+        let interrupted = computer.execute().unwrap();
         assert_eq!(interrupted, Interrupt::Halt);
         assert_eq!(computer.terminate(), vec![]);
     }
@@ -1,4 +1,5 @@
 use crate::utils::read::read_list;
+use intcode_computer::io::from_iter;
 use intcode_computer::pipe::Pipe;
 use intcode_computer::prelude::*;
 
@@ -12,11 +13,10 @@ fn get_amplifier_sequence_output(
     for phase_setting in phase_settings.iter().cloned() {
         let memory = software.clone();
         let mut computer = IntCodeComputer::new(memory);
-        computer.provide_input(phase_setting.into());
-        computer.provide_input(input_signal);
-        execute! { computer,
-            output { input_signal = computer.take_output() }
-        }
+        let mut inputs = from_iter(vec![phase_setting.into(), input_signal]);
+        computer
+            .run_with(&mut inputs, &mut |output| input_signal = output)
+            .expect("amplifier faulted");
     }
     input_signal
 }
@@ -62,37 +62,31 @@ pub fn find_feedback_output(
     software: &IntcodeMemoryType,
     phase_settings: Vec<i32>,
 ) -> IntcodeMemoryCellType {
-    let pipe = Pipe::new();
+    // each amp reads from its own pipe and writes to the next amp's
+    let pipes: Vec<Pipe> = phase_settings
+        .iter()
+        .map(|phase| {
+            let pipe = Pipe::new();
+            pipe.send((*phase).into());
+            pipe
+        })
+        .collect();
+    pipes[0].send(0);
     let mut amps: Vec<IntCodeComputer> = phase_settings
         .iter()
         .map(|_| IntCodeComputer::new(software.clone()))
         .collect();
-    for (amp, phase) in amps.iter_mut().zip(phase_settings.iter()) {
-        amp.provide_input((*phase).into());
-    }
-    amps[0].provide_input(0);
-    let mut halt_count = 0;
-    while halt_count < amps.len() {
-        for amp in amps.iter_mut() {
-            if !pipe.is_empty() {
-                let input = pipe.receive();
-                amp.provide_input(input.into());
-            }
-            loop {
-                let interrupt = amp.execute();
-                match interrupt {
-                    Interrupt::Output => pipe.send(amp.take_output()),
-                    Interrupt::Halt => {
-                        halt_count += 1;
-                        break;
-                    }
-                    Interrupt::Input => break,
-                };
-            }
+    let mut halted = vec![false; amps.len()];
+    while halted.contains(&false) {
+        for (index, amp) in amps.iter_mut().enumerate() {
+            let output = &pipes[(index + 1) % pipes.len()];
+            let summary = amp
+                .run_with(&mut &pipes[index], &mut &*output)
+                .expect("amplifier faulted");
+            halted[index] = summary.halted;
         }
     }
-    let last_output = pipe.receive();
-    last_output
+    pipes[0].receive()
 }
 
 pub fn find_max_feedback_output(software: IntcodeMemoryType) -> IntcodeMemoryCellType {
@@ -0,0 +1,85 @@
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Span {
+    // index into the list of files that took part in the assembly
+    pub file: usize,
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Diagnostic {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(file: &str, span: Span, message: String) -> Diagnostic {
+        Diagnostic {
+            file: file.to_string(),
+            line: span.line,
+            column: span.column,
+            length: span.length,
+            message,
+        }
+    }
+
+    pub fn render(&self, source: &str) -> String {
+        let line_number = self.line.to_string();
+        let gutter = " ".repeat(line_number.len());
+        let mut result = format!(
+            "error: {}\n{}--> {}:{}:{}\n",
+            self.message, gutter, self.file, self.line, self.column
+        );
+        if let Some(text) = source.lines().nth(self.line - 1) {
+            let underline = format!(
+                "{}{}",
+                " ".repeat(self.column - 1),
+                "^".repeat(self.length.max(1))
+            );
+            result.push_str(&format!("{} |\n", gutter));
+            result.push_str(&format!("{} | {}\n", line_number, text));
+            result.push_str(&format!("{} | {}\n", gutter, underline));
+        }
+        result
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.file, self.line, self.column, self.message
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let span = Span {
+            file: 0,
+            line: 2,
+            column: 5,
+            length: 3,
+        };
+        let diagnostic = Diagnostic::new("prog.is", span, "unknown mnemonic 'FOO'".to_string());
+        assert_eq!(
+            diagnostic.render("main:\n    FOO 1 2\n"),
+            "error: unknown mnemonic 'FOO'\n --> prog.is:2:5\n  |\n2 |     FOO 1 2\n  |     ^^^\n"
+        );
+        assert_eq!(
+            diagnostic.to_string(),
+            "prog.is:2:5: unknown mnemonic 'FOO'"
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use super::lexer::mnemonic;
+use intcode_computer::instruction::Instruction;
+use intcode_computer::isa::{InstructionSet, StandardIsa};
+use intcode_computer::operations::{OpCode, Operation};
+use intcode_computer::parameter::{Parameter, ParameterMode};
+use intcode_computer::{IntcodeMemoryCellType, IntcodeMemoryType};
+use std::collections::{BTreeMap, HashSet};
+
+enum Line {
+    Instruction(Instruction),
+    Data(IntcodeMemoryCellType),
+}
+
+fn decode(
+    memory: &[IntcodeMemoryCellType],
+    address: usize,
+    instruction_set: &dyn InstructionSet,
+) -> Option<Instruction> {
+    let raw = memory[address];
+    let opcode = OpCode::decode(raw, instruction_set).ok()?;
+    // cells like 10099 decode, but would not survive a round trip through the assembler
+    let reencoded: IntcodeMemoryCellType = OpCode {
+        operation: opcode.operation,
+        parameter_modes: opcode.parameter_modes.clone(),
+    }
+    .into();
+    if reencoded != raw {
+        return None;
+    }
+    // nor would writing to an immediate, which the assembler rejects
+    let write_parameter = opcode.operation.write_parameter();
+    if write_parameter.map(|index| opcode.parameter_modes[index]) == Some(ParameterMode::Value) {
+        return None;
+    }
+    let end = address + 1 + opcode.operation.parameter_count();
+    if end > memory.len() {
+        return None;
+    }
+    let parameters = opcode
+        .parameter_modes
+        .iter()
+        .zip(memory[address + 1..end].iter())
+        .map(|(mode, value)| match mode {
+            ParameterMode::Pointer if *value < 0 => None,
+            ParameterMode::Pointer => Some(Parameter::Pointer(*value as usize)),
+            ParameterMode::Value => Some(Parameter::Value(*value)),
+            ParameterMode::Relative => Some(Parameter::Relative(*value)),
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(Instruction {
+        operation: opcode.operation,
+        parameters,
+    })
+}
+
+fn jump_target(instruction: &Instruction) -> Option<usize> {
+    match (instruction.operation, instruction.parameters.get(1)) {
+        (Operation::JumpIfTrue, Some(Parameter::Value(target)))
+        | (Operation::JumpIfFalse, Some(Parameter::Value(target)))
+            if *target >= 0 =>
+        {
+            Some(*target as usize)
+        }
+        _ => None,
+    }
+}
+
+fn format_parameter(parameter: &Parameter) -> String {
+    match parameter {
+        Parameter::Value(value) => format!("^{}", value),
+        Parameter::Pointer(address) => address.to_string(),
+        Parameter::Relative(offset) => format!("~{}", offset),
+    }
+}
+
+pub fn format_instruction(instruction: &Instruction) -> String {
+    let mut result = mnemonic(instruction.operation).to_string();
+    for parameter in instruction.parameters.iter() {
+        result.push(' ');
+        result.push_str(&format_parameter(parameter));
+    }
+    result
+}
+
+pub fn disassemble(intcode: &IntcodeMemoryType) -> String {
+    disassemble_with(intcode, &StandardIsa)
+}
+
+/// Disassembles with the extensions of `instruction_set` as well as the standard operations.
+pub fn disassemble_with(
+    intcode: &IntcodeMemoryType,
+    instruction_set: &dyn InstructionSet,
+) -> String {
+    let mut lines: Vec<(usize, Line)> = Vec::new();
+    let mut address = 0;
+    while address < intcode.len() {
+        match decode(intcode, address, instruction_set) {
+            Some(instruction) => {
+                let length = 1 + instruction.parameters.len();
+                lines.push((address, Line::Instruction(instruction)));
+                address += length;
+            }
+            None => {
+                lines.push((address, Line::Data(intcode[address])));
+                address += 1;
+            }
+        }
+    }
+
+    let instruction_addresses: HashSet<usize> = lines
+        .iter()
+        .filter(|(_, line)| matches!(line, Line::Instruction(_)))
+        .map(|(address, _)| *address)
+        .collect();
+    let labels: BTreeMap<usize, String> = lines
+        .iter()
+        .filter_map(|(_, line)| match line {
+            Line::Instruction(instruction) => jump_target(instruction),
+            Line::Data(_) => None,
+        })
+        .filter(|target| instruction_addresses.contains(target))
+        .map(|target| (target, format!("label{}", target)))
+        .collect();
+
+    let mut result = String::new();
+    let mut pending_data: Vec<String> = Vec::new();
+    for (address, line) in lines {
+        let label = labels.get(&address);
+        if label.is_some() || matches!(line, Line::Instruction(_)) {
+            flush_data(&mut result, &mut pending_data);
+        }
+        if let Some(label) = label {
+            result.push_str(&format!("{}:\n", label));
+        }
+        match line {
+            Line::Instruction(instruction) => {
+                let text = match jump_target(&instruction).and_then(|t| labels.get(&t)) {
+                    Some(label) => format!(
+                        "{} {} {}",
+                        mnemonic(instruction.operation),
+                        format_parameter(&instruction.parameters[0]),
+                        label
+                    ),
+                    None => format_instruction(&instruction),
+                };
+                result.push_str(&format!("    {}\n", text));
+            }
+            Line::Data(value) => pending_data.push(value.to_string()),
+        }
+    }
+    flush_data(&mut result, &mut pending_data);
+    result
+}
+
+fn flush_data(result: &mut String, pending_data: &mut Vec<String>) {
+    if !pending_data.is_empty() {
+        result.push_str(&format!("    .data {}\n", pending_data.join(", ")));
+        pending_data.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assemble, assemble_with};
+    use intcode_computer::isa::ExtendedIsa;
+
+    #[test]
+    fn test_disassemble_instructions() {
+        let intcode = vec![1101, 3, 2, 1, 22201, -1, 1, 0, 99];
+        assert_eq!(
+            disassemble(&intcode),
+            "    ADD ^3 ^2 1\n    ADD ~-1 ~1 ~0\n    HALT\n"
+        );
+    }
+
+    #[test]
+    fn test_disassemble_jump_targets() {
+        let intcode = vec![3, 0, 1005, 0, 0, 99];
+        assert_eq!(
+            disassemble(&intcode),
+            "label0:\n    READ 0\n    JIT 0 label0\n    HALT\n"
+        );
+    }
+
+    #[test]
+    fn test_disassemble_data() {
+        let intcode = vec![99, 0, 10099, -4];
+        assert_eq!(disassemble(&intcode), "    HALT\n    .data 0, 10099, -4\n");
+    }
+
+    #[test]
+    fn test_round_trip_immediate_write() {
+        let intcode = vec![11101, 1, 1, 0, 103, 5, 99];
+        assert_eq!(
+            disassemble(&intcode),
+            "    .data 11101\n    ADD 1 0 103\n    .data 5\n    HALT\n"
+        );
+        assert_eq!(assemble(&disassemble(&intcode)), Ok(intcode));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let intcode: IntcodeMemoryType = include_str!("../../advent2019/src/day13_input.txt")
+            .trim()
+            .split(',')
+            .map(|value| value.parse().unwrap())
+            .collect();
+        assert_eq!(assemble(&disassemble(&intcode)), Ok(intcode));
+    }
+
+    #[test]
+    fn test_extensions() {
+        let mut isa = ExtendedIsa::new();
+        isa.register(10, "DIV", 2, |x: &[i64]| x[0].checked_div(x[1]));
+        isa.register(12, "AND", 2, |x: &[i64]| Some(x[0] & x[1]));
+        let program = "    DIV 7 ^2 8\n    AND ^12 ^10 ~0\n    HALT\n";
+        let intcode = vec![1010, 7, 2, 8, 21112, 12, 10, 0, 99];
+        assert_eq!(assemble_with(program, &isa), Ok(intcode.clone()));
+        assert_eq!(disassemble_with(&intcode, &isa), program);
+        assert!(assemble(program).is_err());
+    }
+}
@@ -1,17 +1,49 @@
-use intcode_computer::operations::Operation;
+use super::diagnostic::{Diagnostic, Span};
+use super::macros::Macro;
+use super::pseudo::{is_pseudo_instruction, lower};
+use intcode_computer::operations::{Extension, Operation};
+use intcode_computer::parameter::ParameterMode;
 use intcode_computer::IntcodeMemoryCellType;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug)]
+const MAX_EXPANSION_DEPTH: usize = 32;
+const MAX_INCLUDE_DEPTH: usize = 16;
+// anything bigger is almost certainly a typo, and would take forever to allocate, so this caps
+// every .zero together too
+const MAX_ZERO_COUNT: usize = 1 << 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Literal(IntcodeMemoryCellType),
+    Symbol(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Token {
     LabelDefinition(String),
-    Int(IntcodeMemoryCellType),
-    Immediate(IntcodeMemoryCellType),
-    LabelReference(String),
-    RelativeReference(IntcodeMemoryCellType),
+    ConstantDefinition(String, IntcodeMemoryCellType),
+    Parameter(ParameterMode, Value),
     Operation(Operation),
+    // followed by its values as parameters on the same line
+    Data,
+    Cells(Vec<IntcodeMemoryCellType>),
 }
 
-fn parse_operation(instr: &str) -> Option<Operation> {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spanned<T> {
+    pub value: T,
+    pub span: Span,
+}
+
+impl Token {
+    pub fn is_parameter(&self) -> bool {
+        matches!(self, Self::Parameter(_, _))
+    }
+}
+
+fn parse_operation(instr: &str, extensions: &[Extension]) -> Option<Operation> {
     match instr.to_ascii_uppercase().as_str() {
         "ADD" => Some(Operation::Add),
         "MUL" => Some(Operation::Multiply),
@@ -20,21 +52,30 @@ fn parse_operation(instr: &str) -> Option<Operation> {
         "JIT" => Some(Operation::JumpIfTrue),
         "JIF" => Some(Operation::JumpIfFalse),
         "LT" => Some(Operation::LessThan),
-        "GT" => Some(Operation::Equals),
+        "EQ" | "GT" => Some(Operation::Equals),
         "REL" => Some(Operation::AdjustRelativeBase),
         "HALT" => Some(Operation::Halt),
-        _ => None,
+        _ => extensions
+            .iter()
+            .find(|extension| extension.mnemonic.eq_ignore_ascii_case(instr))
+            .map(|extension| Operation::Extension(*extension)),
     }
 }
 
-fn parse_label_definition(label: &str) -> Option<String> {
-    let stripped = label.trim();
-    if let Some(label) = parse_label(&stripped[..stripped.len() - 1]) {
-        if stripped.ends_with(':') {
-            return Some(label.to_string());
-        }
+pub fn mnemonic(operation: Operation) -> &'static str {
+    match operation {
+        Operation::Add => "ADD",
+        Operation::Multiply => "MUL",
+        Operation::Input => "READ",
+        Operation::Output => "WRITE",
+        Operation::JumpIfTrue => "JIT",
+        Operation::JumpIfFalse => "JIF",
+        Operation::LessThan => "LT",
+        Operation::Equals => "EQ",
+        Operation::AdjustRelativeBase => "REL",
+        Operation::Halt => "HALT",
+        Operation::Extension(extension) => extension.mnemonic,
     }
-    None
 }
 
 fn parse_label(label: &str) -> Option<String> {
@@ -42,57 +83,513 @@ fn parse_label(label: &str) -> Option<String> {
 
     if !stripped.is_empty()
         && stripped.chars().all(|c| c.is_alphanumeric())
-        && !stripped.chars().next().unwrap().is_digit(10)
+        && !stripped.chars().next().unwrap().is_ascii_digit()
     {
         return Some(stripped.to_string());
     }
     None
 }
 
-fn parse_instruction(line: &str) -> Option<Vec<Token>> {
-    let tokens: Vec<_> = line.split_ascii_whitespace().collect();
-    if tokens.is_empty() {
-        return None;
+fn parse_value(value: &str) -> Option<Value> {
+    match parse_label(value) {
+        Some(symbol) => Some(Value::Symbol(symbol)),
+        None => value.parse().ok().map(Value::Literal),
     }
-    let operation = parse_operation(tokens[0])?;
-    let parameters = tokens[1..]
-        .into_iter()
-        .filter_map(|param| parse_parameter(param));
-    let mut result: Vec<Token> = Vec::new();
-    result.push(Token::Operation(operation));
-    result.extend(parameters);
-    Some(result)
 }
 
 fn parse_parameter(parameter: &str) -> Option<Token> {
-    if parameter.to_ascii_lowercase().starts_with('^') {
-        parameter[1..].parse().ok().map(|i| Token::Immediate(i))
-    } else if parameter.to_ascii_lowercase().starts_with('~') {
-        parameter[1..]
-            .parse()
-            .ok()
-            .map(|i| Token::RelativeReference(i))
-    } else if let Some(label) = parse_label(parameter) {
-        Some(Token::LabelReference(label))
+    if let Some(immediate) = parameter.strip_prefix('^') {
+        parse_value(immediate).map(|value| Token::Parameter(ParameterMode::Value, value))
+    } else if let Some(offset) = parameter.strip_prefix('~') {
+        parse_value(offset).map(|value| Token::Parameter(ParameterMode::Relative, value))
+    } else if parameter.starts_with('[') && parameter.ends_with(']') {
+        parse_label(&parameter[1..parameter.len() - 1])
+            .map(|symbol| Token::Parameter(ParameterMode::Pointer, Value::Symbol(symbol)))
     } else {
-        parameter.parse().ok().map(|i| Token::Int(i))
+        match parse_value(parameter)? {
+            symbol @ Value::Symbol(_) => Some(Token::Parameter(ParameterMode::Value, symbol)),
+            literal => Some(Token::Parameter(ParameterMode::Pointer, literal)),
+        }
+    }
+}
+
+fn parse_quoted(literal: &str) -> Option<String> {
+    let inner = literal.strip_prefix('"')?.strip_suffix('"')?;
+    let mut result = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next()? {
+                'n' => '\n',
+                't' => '\t',
+                '0' => '\0',
+                escaped @ '\\' | escaped @ '"' => escaped,
+                _ => return None,
+            },
+            '"' => return None,
+            c => c,
+        };
+        result.push(c);
     }
+    Some(result)
 }
 
-pub fn tokenize(source: &str) -> Vec<Token> {
+fn parse_string(literal: &str) -> Option<Vec<IntcodeMemoryCellType>> {
+    let string = parse_quoted(literal)?;
+    if !string.is_ascii() {
+        return None;
+    }
+    Some(string.bytes().map(IntcodeMemoryCellType::from).collect())
+}
+
+/// Splits a line into its comma or whitespace separated fields, along with their 1-based columns.
+pub fn fields(line: &str) -> Vec<(usize, &str)> {
     let mut result = Vec::new();
-    for token in source.lines() {
-        if token.is_empty() {
-            continue;
+    let mut start = None;
+    for (index, c) in line.char_indices() {
+        let separator = c == ',' || c.is_whitespace();
+        match start {
+            Some(field_start) if separator => {
+                result.push((field_start + 1, &line[field_start..index]));
+                start = None;
+            }
+            None if !separator => start = Some(index),
+            _ => {}
+        }
+    }
+    if let Some(field_start) = start {
+        result.push((field_start + 1, &line[field_start..]));
+    }
+    result
+}
+
+struct Recording {
+    name: String,
+    span: Span,
+    definition: Macro,
+}
+
+pub struct Lexed {
+    pub tokens: Vec<Spanned<Token>>,
+    pub diagnostics: Vec<Diagnostic>,
+    // the assembled file first, then everything it included in order of appearance
+    pub files: Vec<String>,
+}
+
+struct LineLexer {
+    files: Vec<String>,
+    file: usize,
+    line: usize,
+    includes: Vec<PathBuf>,
+    tokens: Vec<Spanned<Token>>,
+    diagnostics: Vec<Diagnostic>,
+    macros: HashMap<String, Macro>,
+    recording: Option<Recording>,
+    // while expanding a macro, everything is attributed to the outermost invocation
+    expansion: Option<Span>,
+    depth: usize,
+    expansions: usize,
+    extensions: Vec<Extension>,
+    // cells made by .zero so far
+    zeroed: usize,
+}
+
+impl LineLexer {
+    fn span(&self, column: usize, field: &str) -> Span {
+        self.expansion.unwrap_or(Span {
+            file: self.file,
+            line: self.line,
+            column,
+            length: field.len(),
+        })
+    }
+
+    fn push(&mut self, value: Token, column: usize, field: &str) {
+        let span = self.span(column, field);
+        self.tokens.push(Spanned { value, span });
+    }
+
+    fn error(&mut self, column: usize, field: &str, message: String) {
+        let span = self.span(column, field);
+        self.diagnostics
+            .push(Diagnostic::new(&self.files[span.file], span, message));
+    }
+
+    fn lex_source(&mut self, source: &str) {
+        for (index, line) in source.lines().enumerate() {
+            self.line = index + 1;
+            self.lex_line(line);
+        }
+    }
+
+    fn include(&mut self, column: usize, directive: &str, name: &str) {
+        if self.includes.len() == MAX_INCLUDE_DEPTH {
+            return self.error(
+                column,
+                directive,
+                "includes are nested too deeply".to_string(),
+            );
         }
-        if let Some(label) = parse_label_definition(token) {
-            result.push(Token::LabelDefinition(label));
-            continue;
+        let path = Path::new(&self.files[self.file])
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(name);
+        let canonical = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+        if self.includes.contains(&canonical) {
+            let message = format!("'{}' includes itself", path.display());
+            return self.error(column, directive, message);
         }
-        if let Some(instruction) = parse_instruction(token) {
-            result.extend(instruction);
-            continue;
+        let source = match fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(error) => {
+                let message = format!("cannot read '{}': {}", path.display(), error);
+                return self.error(column, directive, message);
+            }
+        };
+        let (outer_file, outer_line) = (self.file, self.line);
+        self.files.push(path.display().to_string());
+        self.file = self.files.len() - 1;
+        self.includes.push(canonical);
+        self.lex_source(&source);
+        self.includes.pop();
+        self.file = outer_file;
+        self.line = outer_line;
+    }
+
+    fn lex_line(&mut self, text: &str) {
+        let fields = fields(text);
+        let recording = match self.recording.as_mut() {
+            Some(recording) => recording,
+            None => return self.lex(text, &fields),
+        };
+        match fields.first() {
+            Some((_, ".endm")) => {
+                let Recording {
+                    name, definition, ..
+                } = self.recording.take().unwrap();
+                self.macros.insert(name, definition);
+            }
+            Some((column, ".macro")) => self.error(
+                *column,
+                ".macro",
+                "macro definitions cannot be nested".to_string(),
+            ),
+            _ => recording.definition.body.push(text.to_string()),
         }
     }
-    result
+
+    fn lex(&mut self, text: &str, fields: &[(usize, &str)]) {
+        let (column, first) = match fields.first() {
+            Some(field) => *field,
+            None => return,
+        };
+        if first.starts_with('.') {
+            self.lex_directive(text, column, first, &fields[1..]);
+        } else if let Some(label) = first.strip_suffix(':') {
+            match parse_label(label) {
+                Some(label) => self.push(Token::LabelDefinition(label), column, first),
+                None => self.error(column, first, format!("invalid label name '{}'", label)),
+            }
+            self.lex(text, &fields[1..]);
+        } else if let Some(operation) = parse_operation(first, &self.extensions) {
+            self.push(Token::Operation(operation), column, first);
+            self.lex_parameters(&fields[1..]);
+        } else if is_pseudo_instruction(first) {
+            let start = self.tokens.len();
+            self.lex_parameters(&fields[1..]);
+            let operands = self.tokens.split_off(start);
+            self.expansions += 1;
+            match lower(first, operands, self.span(column, first), self.expansions) {
+                Ok(tokens) => self.tokens.extend(tokens),
+                Err(message) => self.error(column, first, message),
+            }
+        } else if let Some(definition) = self.macros.get(first).cloned() {
+            self.expand(definition, column, first, &fields[1..]);
+        } else {
+            self.error(column, first, format!("unknown mnemonic '{}'", first));
+        }
+    }
+
+    fn lex_parameters(&mut self, fields: &[(usize, &str)]) {
+        for (column, field) in fields.iter() {
+            match parse_parameter(field) {
+                Some(parameter) => self.push(parameter, *column, field),
+                None => self.error(*column, field, format!("invalid operand '{}'", field)),
+            }
+        }
+    }
+
+    fn expand(&mut self, definition: Macro, column: usize, name: &str, args: &[(usize, &str)]) {
+        if args.len() != definition.params.len() {
+            let message = format!(
+                "{} expects {} argument(s), found {}",
+                name,
+                definition.params.len(),
+                args.len()
+            );
+            return self.error(column, name, message);
+        }
+        if self.depth == MAX_EXPANSION_DEPTH {
+            return self.error(column, name, "macro expansion is too deep".to_string());
+        }
+        self.expansions += 1;
+        let id = self.expansions;
+        let outer = self.expansion;
+        self.expansion = Some(self.span(column, name));
+        self.depth += 1;
+        let args: Vec<&str> = args.iter().map(|(_, arg)| *arg).collect();
+        for line in definition.body.iter() {
+            let expanded = definition.expand_line(line, &args, id);
+            self.lex(&expanded, &fields(&expanded));
+        }
+        self.depth -= 1;
+        self.expansion = outer;
+    }
+
+    fn start_macro(&mut self, column: usize, directive: &str, arguments: &[(usize, &str)]) {
+        let name = match arguments.first() {
+            Some((_, name)) => *name,
+            None => return self.error(column, directive, ".macro expects a name".to_string()),
+        };
+        if parse_label(name).is_none()
+            || parse_operation(name, &self.extensions).is_some()
+            || is_pseudo_instruction(name)
+        {
+            let message = format!("'{}' cannot be used as a macro name", name);
+            return self.error(column, directive, message);
+        }
+        let mut params = Vec::new();
+        for (column, param) in arguments[1..].iter() {
+            match parse_label(param) {
+                Some(param) => params.push(param),
+                None => self.error(*column, param, format!("invalid parameter '{}'", param)),
+            }
+        }
+        self.recording = Some(Recording {
+            name: name.to_string(),
+            span: self.span(column, directive),
+            definition: Macro {
+                params,
+                body: Vec::new(),
+            },
+        });
+    }
+
+    fn lex_directive(
+        &mut self,
+        text: &str,
+        column: usize,
+        directive: &str,
+        arguments: &[(usize, &str)],
+    ) {
+        match directive {
+            ".macro" => self.start_macro(column, directive, arguments),
+            ".endm" => self.error(column, directive, ".endm without .macro".to_string()),
+            ".include" => {
+                let literal = text[column - 1 + directive.len()..].trim();
+                match parse_quoted(literal) {
+                    Some(name) => self.include(column, directive, &name),
+                    None => self.error(
+                        column,
+                        directive,
+                        ".include expects a quoted file name".to_string(),
+                    ),
+                }
+            }
+            ".data" => {
+                self.push(Token::Data, column, directive);
+                for (column, field) in arguments.iter() {
+                    match parse_value(field) {
+                        Some(value) => self.push(
+                            Token::Parameter(ParameterMode::Value, value),
+                            *column,
+                            field,
+                        ),
+                        None => self.error(*column, field, format!("invalid value '{}'", field)),
+                    }
+                }
+            }
+            ".zero" => match arguments {
+                [(count_column, count)] if count.parse::<usize>().is_ok() => {
+                    match count.parse().unwrap() {
+                        zeros if zeros > MAX_ZERO_COUNT => self.error(
+                            *count_column,
+                            count,
+                            format!(".zero count is larger than {}", MAX_ZERO_COUNT),
+                        ),
+                        zeros if zeros > MAX_ZERO_COUNT - self.zeroed => self.error(
+                            *count_column,
+                            count,
+                            format!(".zero directives add up to more than {}", MAX_ZERO_COUNT),
+                        ),
+                        zeros => {
+                            self.zeroed += zeros;
+                            self.push(Token::Cells(vec![0; zeros]), column, directive)
+                        }
+                    }
+                }
+                _ => self.error(column, directive, ".zero expects a count".to_string()),
+            },
+            ".string" => {
+                let literal = text[column - 1 + directive.len()..].trim();
+                match parse_string(literal) {
+                    Some(cells) => self.push(Token::Cells(cells), column, directive),
+                    None => self.error(
+                        column,
+                        directive,
+                        ".string expects a quoted ASCII string".to_string(),
+                    ),
+                }
+            }
+            ".equ" => match arguments {
+                [(_, name), (_, value)] => match (parse_label(name), value.parse()) {
+                    (Some(name), Ok(value)) => {
+                        self.push(Token::ConstantDefinition(name, value), column, directive)
+                    }
+                    _ => self.error(
+                        column,
+                        directive,
+                        ".equ expects a name and an integer value".to_string(),
+                    ),
+                },
+                _ => self.error(
+                    column,
+                    directive,
+                    ".equ expects a name and an integer value".to_string(),
+                ),
+            },
+            _ => self.error(
+                column,
+                directive,
+                format!("unknown directive '{}'", directive),
+            ),
+        }
+    }
+}
+
+pub fn tokenize(source: &str, file: &str, extensions: &[Extension]) -> Lexed {
+    let mut lexer = LineLexer {
+        files: vec![file.to_string()],
+        file: 0,
+        line: 0,
+        includes: vec![fs::canonicalize(file).unwrap_or_else(|_| PathBuf::from(file))],
+        tokens: Vec::new(),
+        diagnostics: Vec::new(),
+        macros: HashMap::new(),
+        recording: None,
+        expansion: None,
+        depth: 0,
+        expansions: 0,
+        extensions: extensions.to_vec(),
+        zeroed: 0,
+    };
+    lexer.lex_source(source);
+    if let Some(recording) = lexer.recording.take() {
+        let message = format!("macro '{}' is missing .endm", recording.name);
+        let file = &lexer.files[recording.span.file];
+        lexer
+            .diagnostics
+            .push(Diagnostic::new(file, recording.span, message));
+    }
+    Lexed {
+        tokens: lexer.tokens,
+        diagnostics: lexer.diagnostics,
+        files: lexer.files,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(source: &str) -> Vec<Token> {
+        let lexed = tokenize(source, "test.is", &[]);
+        assert_eq!(lexed.diagnostics, vec![]);
+        lexed.tokens.into_iter().map(|token| token.value).collect()
+    }
+
+    #[test]
+    fn test_fields() {
+        assert_eq!(
+            fields("  ADD 1,2  ~3"),
+            vec![(3, "ADD"), (7, "1"), (9, "2"), (12, "~3")]
+        );
+        assert_eq!(fields("   "), vec![]);
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            values("main:  JIT ^1 main\n   \n.data 4, main"),
+            vec![
+                Token::LabelDefinition("main".to_string()),
+                Token::Operation(Operation::JumpIfTrue),
+                Token::Parameter(ParameterMode::Value, Value::Literal(1)),
+                Token::Parameter(ParameterMode::Value, Value::Symbol("main".to_string())),
+                Token::Data,
+                Token::Parameter(ParameterMode::Value, Value::Literal(4)),
+                Token::Parameter(ParameterMode::Value, Value::Symbol("main".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_parameter() {
+        let symbol = |name: &str| Value::Symbol(name.to_string());
+        assert_eq!(
+            parse_parameter("[score]"),
+            Some(Token::Parameter(ParameterMode::Pointer, symbol("score")))
+        );
+        assert_eq!(
+            parse_parameter("~top"),
+            Some(Token::Parameter(ParameterMode::Relative, symbol("top")))
+        );
+        assert_eq!(
+            parse_parameter("^size"),
+            Some(Token::Parameter(ParameterMode::Value, symbol("size")))
+        );
+        assert_eq!(
+            parse_parameter("-3"),
+            Some(Token::Parameter(ParameterMode::Pointer, Value::Literal(-3)))
+        );
+        assert_eq!(parse_parameter("[3]"), None);
+    }
+
+    #[test]
+    fn test_directives() {
+        assert_eq!(
+            values(".zero 3\nmsg: .string \"A, b\\n\"\n.equ SIZE -4"),
+            vec![
+                Token::Cells(vec![0, 0, 0]),
+                Token::LabelDefinition("msg".to_string()),
+                Token::Cells(vec![65, 44, 32, 98, 10]),
+                Token::ConstantDefinition("SIZE".to_string(), -4),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_errors() {
+        let lexed = tokenize(
+            "ADD 1 2 3\n  FOO 1\nADD 1 $2 3\n.zero x\n.string abc\n.equ 1 2\n.org 4",
+            "test.is",
+            &[],
+        );
+        let locations: Vec<_> = lexed
+            .diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.line, diagnostic.column, diagnostic.length))
+            .collect();
+        assert_eq!(
+            locations,
+            vec![
+                (2, 3, 3),
+                (3, 7, 2),
+                (4, 1, 5),
+                (5, 1, 7),
+                (6, 1, 4),
+                (7, 1, 4)
+            ]
+        );
+    }
 }
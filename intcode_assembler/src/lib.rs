@@ -1,72 +1,218 @@
-use intcode_computer::operations::OpCode;
+use intcode_computer::isa::{InstructionSet, StandardIsa};
+use intcode_computer::operations::{Extension, OpCode};
 use intcode_computer::parameter::ParameterMode;
 use intcode_computer::{IntcodeMemoryCellType, IntcodeMemoryType};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
+mod diagnostic;
+mod disassembler;
 mod lexer;
+mod macros;
+mod object;
+mod pseudo;
+mod source_map;
 
-use lexer::{tokenize, Token};
+pub use diagnostic::{Diagnostic, Span};
+pub use disassembler::{disassemble, disassemble_with, format_instruction};
+use lexer::{tokenize, Lexed, Spanned, Token, Value};
+pub use object::{link, LinkError, Object};
+pub use source_map::SourceMap;
 
 enum Temp {
     Resolved(IntcodeMemoryCellType),
-    LabelReference(String),
+    SymbolReference(String, Span),
 }
 
-pub fn assemble(code: &str) -> IntcodeMemoryType {
+impl Temp {
+    fn from_value(value: &Value, span: Span) -> Temp {
+        match value {
+            Value::Literal(i) => Temp::Resolved(*i),
+            Value::Symbol(symbol) => Temp::SymbolReference(symbol.clone(), span),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assembly {
+    pub intcode: IntcodeMemoryType,
+    pub labels: HashMap<String, usize>,
+    pub source_map: SourceMap,
+}
+
+pub fn assemble(code: &str) -> Result<IntcodeMemoryType, Vec<Diagnostic>> {
+    assemble_file("<input>", code).map(|assembly| assembly.intcode)
+}
+
+pub fn assemble_file(file: &str, code: &str) -> Result<Assembly, Vec<Diagnostic>> {
+    assemble_file_with(file, code, &StandardIsa)
+}
+
+pub fn assemble_with(
+    code: &str,
+    instruction_set: &dyn InstructionSet,
+) -> Result<IntcodeMemoryType, Vec<Diagnostic>> {
+    assemble_file_with("<input>", code, instruction_set).map(|assembly| assembly.intcode)
+}
+
+/// Assembles with the mnemonics of `instruction_set`'s extensions as well as the standard ones.
+pub fn assemble_file_with(
+    file: &str,
+    code: &str,
+    instruction_set: &dyn InstructionSet,
+) -> Result<Assembly, Vec<Diagnostic>> {
+    let object = compile(file, code, false, &instruction_set.extensions())?;
+    Ok(Assembly {
+        intcode: object.code,
+        labels: object.labels.into_iter().collect(),
+        source_map: object.source_map,
+    })
+}
+
+/// Assembles a module for `link`, leaving symbols it does not define to other modules. Like
+/// `assemble_file_with`, the mnemonics of `instruction_set`'s extensions are accepted too.
+pub fn assemble_object(
+    file: &str,
+    code: &str,
+    instruction_set: &dyn InstructionSet,
+) -> Result<Object, Vec<Diagnostic>> {
+    compile(file, code, true, &instruction_set.extensions())
+}
+
+fn compile(
+    file: &str,
+    code: &str,
+    external_symbols: bool,
+    extensions: &[Extension],
+) -> Result<Object, Vec<Diagnostic>> {
     let mut result: Vec<Temp> = Vec::new();
-    let mut address_map: HashMap<String, usize> = HashMap::new();
-    let tokens = tokenize(code);
-    let mut tokens_iter = tokens.iter();
-    while let Some(token) = tokens_iter.next() {
+    let mut address_map: BTreeMap<String, usize> = BTreeMap::new();
+    let mut constants: HashMap<String, IntcodeMemoryCellType> = HashMap::new();
+    let Lexed {
+        tokens,
+        mut diagnostics,
+        files,
+    } = tokenize(code, file, extensions);
+    let mut source_map = SourceMap::default();
+    let diagnostic = |span: Span, message| Diagnostic::new(&files[span.file], span, message);
+    let mut tokens_iter = tokens.iter().peekable();
+    while let Some(Spanned { value: token, span }) = tokens_iter.next() {
+        let mut params = Vec::new();
+        while let Some(param) = tokens_iter.next_if(|next| {
+            (next.span.file, next.span.line) == (span.file, span.line) && next.value.is_parameter()
+        }) {
+            params.push(param);
+        }
         match token {
+            Token::LabelDefinition(name) | Token::ConstantDefinition(name, _)
+                if address_map.contains_key(name) || constants.contains_key(name) =>
+            {
+                let message = format!("duplicate definition of '{}'", name);
+                diagnostics.push(diagnostic(*span, message));
+            }
             Token::LabelDefinition(label) => {
                 address_map.insert(label.clone(), result.len());
             }
+            Token::ConstantDefinition(name, value) => {
+                constants.insert(name.clone(), *value);
+            }
             Token::Operation(operation) => {
-                let expected_parameters = operation.parameter_count() as usize;
-                let mut params = Vec::new();
-                for _ in 0..expected_parameters {
-                    params.push(tokens_iter.next().unwrap());
+                let expected_parameters = operation.parameter_count();
+                if params.len() != expected_parameters {
+                    let message = format!(
+                        "{} expects {} operand(s), found {}",
+                        lexer::mnemonic(*operation),
+                        expected_parameters,
+                        params.len()
+                    );
+                    diagnostics.push(diagnostic(*span, message));
+                    continue;
+                }
+                let mut parameter_modes: Vec<ParameterMode> = Vec::new();
+                let mut values = Vec::new();
+                for param in params.iter() {
+                    if let Token::Parameter(mode, value) = &param.value {
+                        parameter_modes.push(*mode);
+                        values.push(Temp::from_value(value, param.span));
+                    }
+                }
+                if let Some(write_target) = operation.write_parameter() {
+                    if parameter_modes[write_target] == ParameterMode::Value {
+                        let message = "immediate value used as write target".to_string();
+                        let target_span = params[write_target].span;
+                        diagnostics.push(diagnostic(target_span, message));
+                    }
                 }
-                let parameter_modes: Vec<ParameterMode> = params
-                    .iter()
-                    .map(|token| match token {
-                        Token::Immediate(_) | Token::LabelReference(_) => ParameterMode::Value,
-                        Token::Int(_) => ParameterMode::Pointer,
-                        Token::RelativeReference(_) => ParameterMode::Relative,
-                        _ => panic!("not a parmeter"),
-                    })
-                    .collect();
                 let opcode = OpCode {
                     operation: *operation,
-                    parameter_modes: parameter_modes,
+                    parameter_modes,
                 };
+                source_map
+                    .lines
+                    .insert(result.len(), (span.file, span.line));
                 result.push(Temp::Resolved(opcode.into()));
-                for token in params {
-                    match token {
-                        Token::Immediate(i) | Token::Int(i) | Token::RelativeReference(i) => {
-                            result.push(Temp::Resolved(*i))
-                        }
-                        Token::LabelReference(label) => {
-                            result.push(Temp::LabelReference(label.clone()))
-                        }
-                        _ => panic!("unexpected token as parameter"),
+                result.extend(values);
+            }
+            Token::Data => {
+                source_map
+                    .lines
+                    .insert(result.len(), (span.file, span.line));
+                for param in params {
+                    if let Token::Parameter(_, value) = &param.value {
+                        result.push(Temp::from_value(value, param.span));
                     }
                 }
             }
-            _ => panic!("unexpected token"),
+            Token::Cells(cells) => {
+                source_map
+                    .lines
+                    .insert(result.len(), (span.file, span.line));
+                result.extend(cells.iter().map(|cell| Temp::Resolved(*cell)));
+            }
+            Token::Parameter(_, _) => {
+                let message = "operand without an instruction".to_string();
+                diagnostics.push(diagnostic(*span, message));
+            }
         }
     }
-    result
+    let mut relocations = Vec::new();
+    let mut references = Vec::new();
+    let intcode = result
         .iter()
-        .map(|token| match token {
+        .enumerate()
+        .map(|(address, token)| match token {
             Temp::Resolved(i) => *i,
-            Temp::LabelReference(label) => *address_map
-                .get(label)
-                .expect(&format!("label: '{}' not found", label))
-                as IntcodeMemoryCellType,
+            Temp::SymbolReference(symbol, span) => {
+                if let Some(label_address) = address_map.get(symbol) {
+                    relocations.push(address);
+                    *label_address as IntcodeMemoryCellType
+                } else if let Some(value) = constants.get(symbol) {
+                    *value
+                } else if external_symbols {
+                    references.push((address, symbol.clone()));
+                    0
+                } else {
+                    let message = format!("undefined symbol '{}'", symbol);
+                    diagnostics.push(diagnostic(*span, message));
+                    0
+                }
+            }
         })
-        .collect()
+        .collect();
+    if !diagnostics.is_empty() {
+        diagnostics.sort_by(|a, b| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)));
+        return Err(diagnostics);
+    }
+    for (label, address) in address_map.iter() {
+        source_map.insert_label(label, *address);
+    }
+    source_map.files = files;
+    Ok(Object {
+        code: intcode,
+        labels: address_map,
+        source_map,
+        relocations,
+        references,
+    })
 }
 
 #[cfg(test)]
@@ -75,36 +221,262 @@ mod tests {
     #[test]
     fn test_basic_program() {
         let program = "ADD 1 2 3";
-        assert_eq!(assemble(program), vec![1, 1, 2, 3]);
+        assert_eq!(assemble(program).unwrap(), vec![1, 1, 2, 3]);
     }
 
     #[test]
     fn test_multiline_program() {
         let program = "ADD 1 2 3\nMUL 2 3 4\nJIT 2 1";
-        assert_eq!(assemble(program), vec![1, 1, 2, 3, 2, 2, 3, 4, 5, 2, 1]);
+        assert_eq!(
+            assemble(program).unwrap(),
+            vec![1, 1, 2, 3, 2, 2, 3, 4, 5, 2, 1]
+        );
     }
 
     #[test]
     fn test_immediate_values() {
-        let program = "ADD 0 ^2 ^5";
-        assert_eq!(assemble(program), vec![11001, 0, 2, 5]);
+        let program = "ADD ^2 ^5 0";
+        assert_eq!(assemble(program).unwrap(), vec![1101, 2, 5, 0]);
     }
 
     #[test]
     fn test_includes_blank_lines() {
-        let program = "\n\nADD 0 ^2 ^5\nMUL 1 2 3";
-        assert_eq!(assemble(program), vec![11001, 0, 2, 5, 2, 1, 2, 3]);
+        let program = "\n\nADD ^2 ^5 0\nMUL 1 2 3";
+        assert_eq!(assemble(program).unwrap(), vec![1101, 2, 5, 0, 2, 1, 2, 3]);
     }
 
     #[test]
     fn test_label_reference() {
         let program = "main:\nADD 2 1 2\nJIT 1 main";
-        assert_eq!(assemble(program), vec![1, 2, 1, 2, 1005, 1, 0]);
+        assert_eq!(assemble(program).unwrap(), vec![1, 2, 1, 2, 1005, 1, 0]);
+    }
+
+    #[test]
+    fn test_labels_are_returned() {
+        let program = "ADD 0 0 0\nmain:\nHALT";
+        let assembly = assemble_file("test.is", program).unwrap();
+        assert_eq!(assembly.intcode, vec![1, 0, 0, 0, 99]);
+        assert_eq!(assembly.labels.get("main"), Some(&4));
+    }
+
+    #[test]
+    fn test_data_directive() {
+        let program = "HALT\n.data 1, -2 3";
+        assert_eq!(assemble(program).unwrap(), vec![99, 1, -2, 3]);
     }
 
     #[test]
     fn test_relative_mode() {
         let program = "ADD ~-1 ~1 ~0";
-        assert_eq!(assemble(program), vec![22201, -1, 1, 0]);
+        assert_eq!(assemble(program).unwrap(), vec![22201, -1, 1, 0]);
+    }
+
+    fn messages(program: &str) -> Vec<(usize, usize, String)> {
+        assemble(program)
+            .unwrap_err()
+            .into_iter()
+            .map(|diagnostic| (diagnostic.line, diagnostic.column, diagnostic.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_wrong_operand_count() {
+        assert_eq!(
+            messages("ADD 1 2\nHALT 4"),
+            vec![
+                (1, 1, "ADD expects 3 operand(s), found 2".to_string()),
+                (2, 1, "HALT expects 0 operand(s), found 1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_label_errors() {
+        assert_eq!(
+            messages("main:\nmain:\nJIT ^1 loop"),
+            vec![
+                (2, 1, "duplicate definition of 'main'".to_string()),
+                (3, 8, "undefined symbol 'loop'".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_immediate_write_target() {
+        assert_eq!(
+            messages("ADD 1 2 ^3\nREAD main\nmain:"),
+            vec![
+                (1, 9, "immediate value used as write target".to_string()),
+                (2, 6, "immediate value used as write target".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_storage_directives() {
+        let program = "\
+            ADD [counter] ^1 [counter]
+            WRITE [counter]
+            HALT
+        counter:
+            .data 41
+        table:
+            .data counter, SIZE, -1
+            .zero 2
+        .equ SIZE 2";
+        assert_eq!(
+            assemble(program).unwrap(),
+            vec![1001, 7, 1, 7, 4, 7, 99, 41, 7, 2, -1, 0, 0]
+        );
+        assert_eq!(
+            messages("HALT\n.zero 99999999999"),
+            vec![(2, 7, ".zero count is larger than 1048576".to_string())]
+        );
+        assert_eq!(
+            messages(".macro BIG\n.zero 600000\n.endm\nBIG\nHALT\n  BIG"),
+            vec![(
+                6,
+                3,
+                ".zero directives add up to more than 1048576".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_constants_as_operands() {
+        let program = ".equ TILE 3\n.equ BASE 100\nEQ ~BASE ^TILE 0\nREL TILE";
+        assert_eq!(assemble(program).unwrap(), vec![1208, 100, 3, 0, 109, 3]);
+    }
+
+    #[test]
+    fn test_string_directive() {
+        let program = "msg: .string \"Hi\\n\"\n.data msg";
+        assert_eq!(assemble(program).unwrap(), vec![72, 105, 10, 0]);
+    }
+
+    #[test]
+    fn test_duplicate_constant() {
+        assert_eq!(
+            messages("size:\n.equ size 4"),
+            vec![(2, 1, "duplicate definition of 'size'".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_pseudo_instructions() {
+        assert_eq!(assemble("MOV ^7 9").unwrap(), vec![1101, 7, 0, 9]);
+        assert_eq!(assemble("main: JMP main").unwrap(), vec![1105, 1, 0]);
+        assert_eq!(
+            assemble("PUSH 3\nPOP ~2").unwrap(),
+            vec![21001, 3, 0, 0, 109, 1, 109, -1, 21201, 0, 0, 2]
+        );
+        assert_eq!(
+            messages("MOV 1"),
+            vec![(1, 1, "MOV expects 2 operand(s), found 1".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_call_and_return() {
+        use intcode_computer::{Computer, IntCodeComputer, Interrupt};
+
+        let program = "\
+            REL ^stack
+            READ [x]
+            CALL double
+            CALL double
+            WRITE [x]
+            HALT
+        double:
+            PUSH [x]
+            POP ~1
+            ADD ~1 ~1 [x]
+            RET
+        x:
+            .data 0
+        stack:
+            .zero 8";
+        let mut computer = IntCodeComputer::new(assemble(program).unwrap());
+        computer.provide_input(5);
+        assert_eq!(computer.execute(), Ok(Interrupt::Output));
+        assert_eq!(computer.take_output(), 20);
+        assert_eq!(computer.execute(), Ok(Interrupt::Halt));
+    }
+
+    #[test]
+    fn test_macros() {
+        let program = "\
+        .macro INC target
+            ADD target ^1 target
+        .endm
+        .macro COUNTDOWN counter
+        loop\\@:
+            ADD counter ^-1 counter
+            JIT counter loop\\@
+        .endm
+            INC [x]
+            COUNTDOWN 9
+            COUNTDOWN 9
+        x:";
+        assert_eq!(
+            assemble(program).unwrap(),
+            vec![1001, 18, 1, 18, 1001, 9, -1, 9, 1005, 9, 4, 1001, 9, -1, 9, 1005, 9, 11]
+        );
+    }
+
+    #[test]
+    fn test_macro_errors() {
+        let program = "\
+.macro TWICE a
+    ADD a a
+    ADD a a
+.endm
+TWICE 1
+TWICE
+.macro ADD x
+.endm
+.macro OPEN";
+        assert_eq!(
+            messages(program),
+            vec![
+                (5, 1, "ADD expects 3 operand(s), found 2".to_string()),
+                (5, 1, "ADD expects 3 operand(s), found 2".to_string()),
+                (6, 1, "TWICE expects 1 argument(s), found 0".to_string()),
+                (7, 1, "'ADD' cannot be used as a macro name".to_string()),
+                (8, 1, ".endm without .macro".to_string()),
+                (9, 1, "macro 'OPEN' is missing .endm".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_include() {
+        let directory =
+            std::env::temp_dir().join(format!("intcode_include_{}", std::process::id()));
+        std::fs::create_dir_all(directory.join("lib")).unwrap();
+        let library = directory.join("lib").join("print.is");
+        std::fs::write(&library, "print: WRITE ^7\n    RET\n    FOO\n").unwrap();
+        std::fs::write(
+            directory.join("lib").join("loop.is"),
+            ".include \"loop.is\"",
+        )
+        .unwrap();
+        let main = directory.join("main.is");
+        let main = main.to_str().unwrap();
+
+        let assembly = assemble_file(main, "CALL print\nHALT\n.include \"lib/print.is\"");
+        let diagnostics = assembly.unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].file, library.to_str().unwrap());
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (3, 5));
+
+        std::fs::write(&library, "print: WRITE ^7\n    RET\n").unwrap();
+        let assembly = assemble_file(main, "CALL print\nHALT\n.include \"lib/print.is\"");
+        assert_eq!(assembly.unwrap().labels.get("print"), Some(&10));
+        let (_, _, message) = &messages(".include \"missing.is\"")[0];
+        assert!(message.starts_with("cannot read 'missing.is'"));
+        let diagnostics = assemble_file(main, ".include \"lib/loop.is\"").unwrap_err();
+        assert!(diagnostics[0].message.ends_with("loop.is' includes itself"));
+        std::fs::remove_dir_all(directory).unwrap();
     }
 }
@@ -0,0 +1,66 @@
+use super::lexer::fields;
+
+#[derive(Debug, Clone)]
+pub struct Macro {
+    pub params: Vec<String>,
+    pub body: Vec<String>,
+}
+
+fn substitute_field(field: &str, params: &[String], args: &[&str], id: usize) -> String {
+    let field = field.replace("\\@", &id.to_string());
+    let (prefix, core, suffix) = if let Some(core) = field.strip_prefix('^') {
+        ("^", core, "")
+    } else if let Some(core) = field.strip_prefix('~') {
+        ("~", core, "")
+    } else if field.starts_with('[') && field.ends_with(']') && field.len() >= 2 {
+        ("[", &field[1..field.len() - 1], "]")
+    } else {
+        ("", field.as_str(), "")
+    };
+    match params.iter().position(|param| param == core) {
+        Some(index) => format!("{}{}{}", prefix, args[index], suffix),
+        None => field,
+    }
+}
+
+impl Macro {
+    /// Replaces parameters and `\@` in a body line, leaving everything between fields untouched.
+    pub fn expand_line(&self, line: &str, args: &[&str], id: usize) -> String {
+        let mut result = String::new();
+        let mut copied_to = 0;
+        for (column, field) in fields(line) {
+            let start = column - 1;
+            result.push_str(&line[copied_to..start]);
+            result.push_str(&substitute_field(field, &self.params, args, id));
+            copied_to = start + field.len();
+        }
+        result.push_str(&line[copied_to..]);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_line() {
+        let definition = Macro {
+            params: vec!["a".to_string(), "b".to_string()],
+            body: vec![],
+        };
+        let args = ["x", "5"];
+        assert_eq!(
+            definition.expand_line("  ADD a, ~b [a] ab", &args, 3),
+            "  ADD x, ~5 [x] ab"
+        );
+        assert_eq!(
+            definition.expand_line("MOV a b", &["[x]", "~1"], 3),
+            "MOV [x] ~1"
+        );
+        assert_eq!(
+            definition.expand_line("loop\\@: JIT b loop\\@", &args, 3),
+            "loop3: JIT 5 loop3"
+        );
+    }
+}
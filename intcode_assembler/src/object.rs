@@ -0,0 +1,230 @@
+use super::{Assembly, SourceMap};
+use intcode_computer::{IntcodeMemoryCellType, IntcodeMemoryType};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+const HEADER: &str = "intcode object";
+
+/// An assembled module whose addresses all assume it is loaded at address 0.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Object {
+    pub code: IntcodeMemoryType,
+    pub labels: BTreeMap<String, usize>,
+    pub source_map: SourceMap,
+    // addresses holding a label's address, which move along with the module
+    pub relocations: Vec<usize>,
+    // addresses holding a symbol that has to come from another module
+    pub references: Vec<(usize, String)>,
+}
+
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let code: Vec<String> = self.code.iter().map(|cell| cell.to_string()).collect();
+        writeln!(f, "{}", HEADER)?;
+        writeln!(f, "code {}", code.join(","))?;
+        for (label, address) in self.labels.iter() {
+            writeln!(f, "label {} {}", label, address)?;
+        }
+        self.source_map.fmt_entries(f)?;
+        for address in self.relocations.iter() {
+            writeln!(f, "relocation {}", address)?;
+        }
+        for (address, symbol) in self.references.iter() {
+            writeln!(f, "reference {} {}", address, symbol)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_address(field: &str, code_length: usize) -> Result<usize, String> {
+    match field.parse() {
+        Ok(address) if address < code_length => Ok(address),
+        _ => Err(format!("invalid address '{}'", field)),
+    }
+}
+
+impl FromStr for Object {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Object, String> {
+        let mut lines = source.lines().enumerate();
+        match lines.next() {
+            Some((_, HEADER)) => {}
+            _ => return Err("not an intcode object file".to_string()),
+        }
+        let mut object = Object::default();
+        for (index, line) in lines {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let parsed = match (object.source_map.parse_entry(line), fields.as_slice()) {
+                (Ok(true), _) | (Ok(false), []) | (Ok(false), ["code"]) => Ok(()),
+                (Err(error), _) => Err(error),
+                (Ok(false), ["code", cells]) => cells
+                    .split(',')
+                    .map(|cell| cell.parse::<IntcodeMemoryCellType>())
+                    .collect::<Result<_, _>>()
+                    .map(|code| object.code = code)
+                    .map_err(|error| format!("invalid code: {}", error)),
+                (Ok(false), ["label", label, address]) => {
+                    parse_address(address, object.code.len() + 1).map(|address| {
+                        object.labels.insert(label.to_string(), address);
+                        object.source_map.insert_label(label, address);
+                    })
+                }
+                (Ok(false), ["relocation", address]) => parse_address(address, object.code.len())
+                    .map(|address| object.relocations.push(address)),
+                (Ok(false), ["reference", address, symbol]) => {
+                    parse_address(address, object.code.len())
+                        .map(|address| object.references.push((address, symbol.to_string())))
+                }
+                _ => Err(format!("unexpected '{}'", line.trim())),
+            };
+            parsed.map_err(|error| format!("line {}: {}", index + 1, error))?;
+        }
+        Ok(object)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    UndefinedSymbol { module: usize, symbol: String },
+    AmbiguousSymbol { module: usize, symbol: String },
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkError::UndefinedSymbol { symbol, .. } => {
+                write!(f, "undefined symbol '{}'", symbol)
+            }
+            LinkError::AmbiguousSymbol { symbol, .. } => {
+                write!(f, "symbol '{}' is defined in more than one module", symbol)
+            }
+        }
+    }
+}
+
+/// Lays the modules out one after another, starting with the first at address 0.
+pub fn link(objects: &[Object]) -> Result<Assembly, Vec<LinkError>> {
+    let mut bases = Vec::new();
+    let mut definitions: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut length = 0;
+    for object in objects.iter() {
+        bases.push(length);
+        for (label, address) in object.labels.iter() {
+            definitions.entry(label).or_default().push(length + address);
+        }
+        length += object.code.len();
+    }
+
+    let mut intcode = Vec::with_capacity(length);
+    let mut source_map = SourceMap::default();
+    let mut errors = Vec::new();
+    for (module, (object, base)) in objects.iter().zip(bases.iter()).enumerate() {
+        source_map.append(&object.source_map, *base);
+        let mut code = object.code.clone();
+        for address in object.relocations.iter() {
+            code[*address] += *base as IntcodeMemoryCellType;
+        }
+        for (address, symbol) in object.references.iter() {
+            match definitions.get(symbol.as_str()).map(Vec::as_slice) {
+                Some([target]) => code[*address] += *target as IntcodeMemoryCellType,
+                Some(_) => errors.push(LinkError::AmbiguousSymbol {
+                    module,
+                    symbol: symbol.clone(),
+                }),
+                None => errors.push(LinkError::UndefinedSymbol {
+                    module,
+                    symbol: symbol.clone(),
+                }),
+            }
+        }
+        intcode.extend(code);
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    let labels = definitions
+        .into_iter()
+        .filter(|(_, addresses)| addresses.len() == 1)
+        .map(|(label, addresses)| (label.to_string(), addresses[0]))
+        .collect();
+    Ok(Assembly {
+        intcode,
+        labels,
+        source_map,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble_object;
+    use intcode_computer::isa::{ExtendedIsa, StandardIsa};
+
+    #[test]
+    fn test_object_round_trip() {
+        let object = assemble_object(
+            "main.is",
+            "main: JIT ^1 main\nJIT ^1 print\nsize: .data 3",
+            &StandardIsa,
+        )
+        .unwrap();
+        assert_eq!(object.code, vec![1105, 1, 0, 1105, 1, 0, 3]);
+        assert_eq!(object.relocations, vec![2]);
+        assert_eq!(object.references, vec![(5, "print".to_string())]);
+        let text = object.to_string();
+        assert_eq!(
+            text,
+            "intcode object\ncode 1105,1,0,1105,1,0,3\nlabel main 0\nlabel size 6\nfile 0 main.is\nline 0 0 1\nline 3 0 2\nline 6 0 3\nrelocation 2\nreference 5 print\n"
+        );
+        assert_eq!(text.parse(), Ok(object));
+        assert_eq!(
+            "intcode object\nrelocation 0".parse::<Object>(),
+            Err("line 2: invalid address '0'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_object_extensions() {
+        let mut isa = ExtendedIsa::new();
+        isa.register(10, "DIV", 2, |x: &[i64]| x[0].checked_div(x[1]));
+        let object = assemble_object("main.is", "DIV 7 ^2 8", &isa).unwrap();
+        assert_eq!(object.code, vec![1010, 7, 2, 8]);
+        assert!(assemble_object("main.is", "DIV 7 ^2 8", &StandardIsa).is_err());
+    }
+
+    #[test]
+    fn test_link() {
+        let main = assemble_object("main.is", "CALL print\nHALT", &StandardIsa).unwrap();
+        let library =
+            assemble_object("lib.is", "print: WRITE ^7\nJIF ^0 print\nRET", &StandardIsa).unwrap();
+        let assembly = link(&[main, library]).unwrap();
+        assert_eq!(assembly.labels.get("print"), Some(&10));
+        assert_eq!(
+            assembly.intcode,
+            vec![21101, 9, 0, 0, 109, 1, 1105, 1, 10, 99, 104, 7, 1106, 0, 10, 109, -1, 2105, 1, 0]
+        );
+    }
+
+    #[test]
+    fn test_link_errors() {
+        let main = assemble_object("main.is", "JIT ^1 print", &StandardIsa).unwrap();
+        let first = assemble_object("a.is", "print: HALT", &StandardIsa).unwrap();
+        let second = assemble_object("b.is", "print: HALT", &StandardIsa).unwrap();
+        assert_eq!(
+            link(std::slice::from_ref(&main)).unwrap_err(),
+            vec![LinkError::UndefinedSymbol {
+                module: 0,
+                symbol: "print".to_string()
+            }]
+        );
+        assert_eq!(
+            link(&[main, first, second]).unwrap_err(),
+            vec![LinkError::AmbiguousSymbol {
+                module: 0,
+                symbol: "print".to_string()
+            }]
+        );
+    }
+}
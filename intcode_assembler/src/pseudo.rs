@@ -0,0 +1,103 @@
+use super::diagnostic::Span;
+use super::lexer::{Spanned, Token, Value};
+use intcode_computer::operations::Operation;
+use intcode_computer::parameter::ParameterMode;
+use intcode_computer::IntcodeMemoryCellType;
+
+// The stack lives wherever the program points the relative base (e.g. `REL ^stack`) and grows
+// upwards, with `~0` always being the next free slot.
+
+fn operand_count(mnemonic: &str) -> Option<usize> {
+    match mnemonic {
+        "MOV" => Some(2),
+        "JMP" | "PUSH" | "POP" | "CALL" => Some(1),
+        "RET" => Some(0),
+        _ => None,
+    }
+}
+
+pub fn is_pseudo_instruction(mnemonic: &str) -> bool {
+    operand_count(&mnemonic.to_ascii_uppercase()).is_some()
+}
+
+pub fn lower(
+    mnemonic: &str,
+    mut operands: Vec<Spanned<Token>>,
+    span: Span,
+    id: usize,
+) -> Result<Vec<Spanned<Token>>, String> {
+    let mnemonic = mnemonic.to_ascii_uppercase();
+    let expected = operand_count(&mnemonic).expect("not a pseudo-instruction");
+    if operands.len() != expected {
+        return Err(format!(
+            "{} expects {} operand(s), found {}",
+            mnemonic,
+            expected,
+            operands.len()
+        ));
+    }
+    let spanned = |value| Spanned { value, span };
+    let operation = |operation| spanned(Token::Operation(operation));
+    let immediate = |value: IntcodeMemoryCellType| {
+        spanned(Token::Parameter(
+            ParameterMode::Value,
+            Value::Literal(value),
+        ))
+    };
+    let top = || spanned(Token::Parameter(ParameterMode::Relative, Value::Literal(0)));
+    let result = match mnemonic.as_str() {
+        "MOV" => {
+            let destination = operands.pop().unwrap();
+            let source = operands.pop().unwrap();
+            vec![operation(Operation::Add), source, immediate(0), destination]
+        }
+        "JMP" => vec![
+            operation(Operation::JumpIfTrue),
+            immediate(1),
+            operands.remove(0),
+        ],
+        "PUSH" => vec![
+            operation(Operation::Add),
+            operands.remove(0),
+            immediate(0),
+            top(),
+            operation(Operation::AdjustRelativeBase),
+            immediate(1),
+        ],
+        "POP" => vec![
+            operation(Operation::AdjustRelativeBase),
+            immediate(-1),
+            operation(Operation::Add),
+            top(),
+            immediate(0),
+            operands.remove(0),
+        ],
+        "CALL" => {
+            let return_label = format!("ret.{}", id);
+            vec![
+                operation(Operation::Add),
+                spanned(Token::Parameter(
+                    ParameterMode::Value,
+                    Value::Symbol(return_label.clone()),
+                )),
+                immediate(0),
+                top(),
+                operation(Operation::AdjustRelativeBase),
+                immediate(1),
+                operation(Operation::JumpIfTrue),
+                immediate(1),
+                operands.remove(0),
+                spanned(Token::LabelDefinition(return_label)),
+            ]
+        }
+        "RET" => vec![
+            operation(Operation::AdjustRelativeBase),
+            immediate(-1),
+            operation(Operation::JumpIfTrue),
+            immediate(1),
+            top(),
+        ],
+        _ => unreachable!(),
+    };
+    Ok(result)
+}
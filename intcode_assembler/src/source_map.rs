@@ -0,0 +1,223 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+const HEADER: &str = "intcode source map";
+
+/// Maps addresses of an assembled program back to the assembly they came from.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SourceMap {
+    pub files: Vec<String>,
+    // the file index and line that each instruction or run of data starts on
+    pub lines: BTreeMap<usize, (usize, usize)>,
+    // every label at each address, in the order they were added
+    pub labels: BTreeMap<usize, Vec<String>>,
+}
+
+// labels the assembler makes up, like the return addresses of CALL, can't be written by hand
+fn is_generated(label: &str) -> bool {
+    label.contains('.')
+}
+
+impl SourceMap {
+    /// The file and line of the instruction or data containing `address`.
+    pub fn location(&self, address: usize) -> Option<(&str, usize)> {
+        let (_, (file, line)) = self.lines.range(..=address).next_back()?;
+        Some((&self.files[*file], *line))
+    }
+
+    /// The closest label at or before `address`, along with how far past it `address` is.
+    /// Labels made up by the assembler are skipped.
+    pub fn nearest_label(&self, address: usize) -> Option<(&str, usize)> {
+        self.labels
+            .range(..=address)
+            .rev()
+            .find_map(|(label_address, labels)| {
+                let label = labels.iter().find(|label| !is_generated(label))?;
+                Some((label.as_str(), address - label_address))
+            })
+    }
+
+    pub fn address_of(&self, label: &str) -> Option<usize> {
+        self.labels
+            .iter()
+            .find(|(_, names)| names.iter().any(|name| name == label))
+            .map(|(address, _)| *address)
+    }
+
+    /// Formats `address` as e.g. `main.is:4 <loop+2>`, leaving out whatever is unknown.
+    pub fn describe(&self, address: usize) -> String {
+        let mut parts = Vec::new();
+        if let Some((file, line)) = self.location(address) {
+            parts.push(format!("{}:{}", file, line));
+        }
+        match self.nearest_label(address) {
+            Some((label, 0)) => parts.push(format!("<{}>", label)),
+            Some((label, offset)) => parts.push(format!("<{}+{}>", label, offset)),
+            None => {}
+        }
+        parts.join(" ")
+    }
+
+    pub(crate) fn insert_label(&mut self, label: &str, address: usize) {
+        let labels = self.labels.entry(address).or_default();
+        if !labels.iter().any(|known| known == label) {
+            labels.push(label.to_string());
+        }
+    }
+
+    /// Adds another module's map, with that module loaded at `base`.
+    pub(crate) fn append(&mut self, other: &SourceMap, base: usize) {
+        let files: Vec<usize> = other
+            .files
+            .iter()
+            .map(
+                |file| match self.files.iter().position(|known| known == file) {
+                    Some(index) => index,
+                    None => {
+                        self.files.push(file.clone());
+                        self.files.len() - 1
+                    }
+                },
+            )
+            .collect();
+        for (address, (file, line)) in other.lines.iter() {
+            self.lines.insert(base + address, (files[*file], *line));
+        }
+        for (address, labels) in other.labels.iter() {
+            for label in labels {
+                self.insert_label(label, base + address);
+            }
+        }
+    }
+
+    pub(crate) fn fmt_entries(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, file) in self.files.iter().enumerate() {
+            writeln!(f, "file {} {}", index, file)?;
+        }
+        for (address, (file, line)) in self.lines.iter() {
+            writeln!(f, "line {} {} {}", address, file, line)?;
+        }
+        Ok(())
+    }
+
+    /// Parses a `file` or `line` entry, returning `Ok(false)` for anything else.
+    pub(crate) fn parse_entry(&mut self, entry: &str) -> Result<bool, String> {
+        let fields: Vec<&str> = entry.splitn(3, ' ').collect();
+        let number = |field: &str| {
+            field
+                .parse::<usize>()
+                .map_err(|_| format!("invalid number '{}'", field))
+        };
+        match fields.as_slice() {
+            ["file", index, name] => {
+                if number(index)? != self.files.len() {
+                    return Err(format!("unexpected file index '{}'", index));
+                }
+                self.files.push(name.to_string());
+            }
+            ["line", address, rest] => {
+                let (file, line) = match rest.split_once(' ') {
+                    Some((file, line)) => (number(file)?, number(line)?),
+                    None => return Err(format!("unexpected '{}'", entry)),
+                };
+                if file >= self.files.len() {
+                    return Err(format!("unknown file index '{}'", file));
+                }
+                self.lines.insert(number(address)?, (file, line));
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+impl fmt::Display for SourceMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        self.fmt_entries(f)?;
+        for (address, labels) in self.labels.iter() {
+            for label in labels {
+                writeln!(f, "label {} {}", address, label)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SourceMap {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<SourceMap, String> {
+        let mut lines = source.lines().enumerate();
+        match lines.next() {
+            Some((_, HEADER)) => {}
+            _ => return Err("not an intcode source map".to_string()),
+        }
+        let mut map = SourceMap::default();
+        for (index, line) in lines {
+            let parsed = match map.parse_entry(line) {
+                Ok(true) => Ok(()),
+                Ok(false) => match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+                    [] => Ok(()),
+                    ["label", address, label] => address
+                        .parse()
+                        .map(|address| map.insert_label(label, address))
+                        .map_err(|_| format!("invalid number '{}'", address)),
+                    _ => Err(format!("unexpected '{}'", line.trim())),
+                },
+                Err(error) => Err(error),
+            };
+            parsed.map_err(|error| format!("line {}: {}", index + 1, error))?;
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assemble_file;
+
+    #[test]
+    fn test_source_map() {
+        let program = "\
+.macro TWICE a
+    WRITE a
+    WRITE a
+.endm
+main:
+    TWICE ^1
+loop:
+    JIT ^1 loop
+    .data 1, 2";
+        let map = assemble_file("main.is", program).unwrap().source_map;
+        assert_eq!(map.location(0), Some(("main.is", 6)));
+        assert_eq!(map.location(3), Some(("main.is", 6)));
+        assert_eq!(map.location(5), Some(("main.is", 8)));
+        assert_eq!(map.location(8), Some(("main.is", 9)));
+        assert_eq!(map.nearest_label(6), Some(("loop", 2)));
+        assert_eq!(map.describe(4), "main.is:8 <loop>");
+        assert_eq!(map.describe(6), "main.is:8 <loop+2>");
+        assert_eq!(map.address_of("loop"), Some(4));
+        assert_eq!(map.to_string().parse(), Ok(map));
+    }
+
+    #[test]
+    fn test_labels_sharing_an_address() {
+        let program = "\
+main:
+loop:
+    CALL print
+    HALT
+print:
+    RET";
+        let map = assemble_file("main.is", program).unwrap().source_map;
+        assert_eq!(map.address_of("main"), Some(0));
+        assert_eq!(map.address_of("loop"), Some(0));
+        // HALT also has the return label CALL makes up, which isn't shown
+        assert_eq!(map.location(9), Some(("main.is", 4)));
+        assert_eq!(map.nearest_label(9).map(|(_, offset)| offset), Some(9));
+        assert_eq!(map.nearest_label(10), Some(("print", 0)));
+        assert_eq!(map.to_string().parse(), Ok(map));
+    }
+}
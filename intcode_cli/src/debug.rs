@@ -0,0 +1,561 @@
+use intcode_assembler::{format_instruction, SourceMap};
+use intcode_computer::condition::Condition;
+use intcode_computer::watch::{Access, Stop, WatchHit, Watchpoint};
+use intcode_computer::{Computer, IntCodeComputer, IntcodeMemoryCellType, Interrupt};
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::io::{self, BufRead, Write};
+
+// how many instructions can be stepped back over
+const HISTORY_LIMIT: usize = 1_000_000;
+// the most memory cells a single `x` shows
+const MEMORY_DISPLAY_LIMIT: usize = 4096;
+
+const HELP: &str = "commands:
+  s, step [N]          execute N instructions (default 1)
+  rs, rstep [N]        step back over N instructions (default 1)
+  c, continue          run until a breakpoint, watchpoint, input request or halt
+  rw, rwrite ADDR      step back to the last instruction that wrote to ADDR
+  b, break LOC [if C]  set a breakpoint at an address or label, only stopping if C holds
+  d, delete LOC        remove a breakpoint
+  breakpoints          list breakpoints
+  w, watch ADDR [if C] stop when ADDR is written, only if C holds
+  rwatch ADDR [if C]   stop when ADDR is read, only if C holds
+  unwatch ADDR         remove the watchpoints on ADDR
+  watchpoints          list watchpoints
+  x, mem ADDR [N]      show N memory cells starting at ADDR (default 1, at most 4096)
+  poke ADDR VALUE      write VALUE to memory at ADDR
+  r, regs              show instruction pointer, relative base and buffers
+  i, input VALUES      queue comma or space separated input values
+  o, output            drain and print pending output
+  q, quit              exit the debugger
+
+conditions compare [ADDR], rb, ip, numbers and, for watchpoints, the value read or written:
+  [386] > 0 && rb == 10 || value == -1";
+
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    Step(usize),
+    StepBack(usize),
+    Continue,
+    BackToWrite(usize),
+    Break(String, Option<Condition>),
+    Delete(String),
+    Breakpoints,
+    Watch(usize, Access, Option<Condition>),
+    Unwatch(usize),
+    Watchpoints,
+    Memory(usize, usize),
+    Poke(usize, IntcodeMemoryCellType),
+    Registers,
+    Input(Vec<IntcodeMemoryCellType>),
+    Output,
+    Help,
+    Quit,
+}
+
+fn parse_number<T: std::str::FromStr>(word: Option<&&str>, what: &str) -> Result<T, String> {
+    let word = word.ok_or_else(|| format!("missing {}", what))?;
+    word.parse()
+        .map_err(|_| format!("'{}' is not a valid {}", word, what))
+}
+
+fn parse_condition(words: &[&str]) -> Result<Option<Condition>, String> {
+    match words.first() {
+        None => Ok(None),
+        Some(&"if") => words[1..].join(" ").parse().map(Some),
+        Some(other) => Err(format!("expected 'if', found '{}'", other)),
+    }
+}
+
+fn parse_command(line: &str) -> Result<Command, String> {
+    let words: Vec<_> = line.split_whitespace().collect();
+    let rest = words.get(1..).unwrap_or(&[]);
+    match words.first().copied().unwrap_or("") {
+        "s" | "step" => match rest.first() {
+            Some(_) => Ok(Command::Step(parse_number(rest.first(), "count")?)),
+            None => Ok(Command::Step(1)),
+        },
+        "rs" | "rstep" => match rest.first() {
+            Some(_) => Ok(Command::StepBack(parse_number(rest.first(), "count")?)),
+            None => Ok(Command::StepBack(1)),
+        },
+        "c" | "continue" => Ok(Command::Continue),
+        "rw" | "rwrite" => Ok(Command::BackToWrite(parse_number(rest.first(), "address")?)),
+        "b" | "break" => match rest.first() {
+            Some(location) => Ok(Command::Break(
+                location.to_string(),
+                parse_condition(&rest[1..])?,
+            )),
+            None => Err("missing breakpoint location".to_string()),
+        },
+        "d" | "delete" => match rest.first() {
+            Some(location) => Ok(Command::Delete(location.to_string())),
+            None => Err("missing breakpoint location".to_string()),
+        },
+        "breakpoints" => Ok(Command::Breakpoints),
+        "w" | "watch" | "rwatch" => Ok(Command::Watch(
+            parse_number(rest.first(), "address")?,
+            if words[0] == "rwatch" {
+                Access::Read
+            } else {
+                Access::Write
+            },
+            parse_condition(rest.get(1..).unwrap_or(&[]))?,
+        )),
+        "unwatch" => Ok(Command::Unwatch(parse_number(rest.first(), "address")?)),
+        "watchpoints" => Ok(Command::Watchpoints),
+        "x" | "mem" => {
+            let address = parse_number(rest.first(), "address")?;
+            let count = match rest.get(1) {
+                Some(_) => parse_number(rest.get(1), "count")?,
+                None => 1,
+            };
+            Ok(Command::Memory(address, count))
+        }
+        "poke" => Ok(Command::Poke(
+            parse_number(rest.first(), "address")?,
+            parse_number(rest.get(1), "value")?,
+        )),
+        "r" | "regs" => Ok(Command::Registers),
+        "i" | "input" => Ok(Command::Input(crate::run::parse_values(&rest.join(" "))?)),
+        "o" | "output" => Ok(Command::Output),
+        "h" | "help" => Ok(Command::Help),
+        "q" | "quit" => Ok(Command::Quit),
+        "" => Err("no command".to_string()),
+        other => Err(format!("unknown command '{}', try 'help'", other)),
+    }
+}
+
+pub struct Debugger {
+    computer: IntCodeComputer,
+    source_map: SourceMap,
+    // lines of every file in the source map that could be read
+    sources: HashMap<String, Vec<String>>,
+}
+
+impl Debugger {
+    pub fn new(mut computer: IntCodeComputer, source_map: SourceMap) -> Debugger {
+        computer.record_history(HISTORY_LIMIT);
+        let sources = source_map
+            .files
+            .iter()
+            .filter_map(|file| {
+                let source = read_to_string(file).ok()?;
+                Some((file.clone(), source.lines().map(String::from).collect()))
+            })
+            .collect();
+        Debugger {
+            computer,
+            source_map,
+            sources,
+        }
+    }
+
+    pub fn repl(&mut self) {
+        let stdin = io::stdin();
+        let mut last_line = String::new();
+        self.show_location();
+        loop {
+            print!("(icdb) ");
+            io::stdout().flush().unwrap();
+            let mut line = String::new();
+            match stdin.lock().read_line(&mut line) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(error) => {
+                    eprintln!("{}", error);
+                    break;
+                }
+            }
+            if line.trim().is_empty() {
+                line = last_line.clone();
+            } else {
+                last_line = line.clone();
+            }
+            match parse_command(&line) {
+                Ok(Command::Quit) => break,
+                Ok(command) => self.handle(command),
+                Err(error) => println!("{}", error),
+            }
+        }
+    }
+
+    fn handle(&mut self, command: Command) {
+        match command {
+            Command::Step(count) => {
+                for _ in 0..count {
+                    if !self.step() {
+                        break;
+                    }
+                }
+                self.show_location();
+            }
+            Command::StepBack(count) => {
+                let undone = self.computer.step_back(count);
+                if undone < count {
+                    println!("reached the start of the history");
+                }
+                self.show_location();
+            }
+            Command::BackToWrite(address) => match self.computer.step_back_to_write(address) {
+                Some(undone) => {
+                    println!("stepped back {} instruction(s)", undone);
+                    self.show_location();
+                }
+                None => println!("no write to {} in the history", address),
+            },
+            Command::Continue => {
+                self.run();
+                self.show_location();
+            }
+            Command::Break(location, condition) => match self.resolve_location(&location) {
+                Some(address) => {
+                    self.computer.set_breakpoint(address, condition);
+                    println!("breakpoint set at {}", address);
+                }
+                None => println!("unknown location '{}'", location),
+            },
+            Command::Delete(location) => match self.resolve_location(&location) {
+                Some(address) if self.computer.remove_breakpoint(address) => {
+                    println!("breakpoint at {} deleted", address)
+                }
+                _ => println!("no breakpoint at '{}'", location),
+            },
+            Command::Breakpoints => {
+                for (address, condition) in self.computer.breakpoints() {
+                    println!(
+                        "{}{}{}",
+                        address,
+                        self.label_suffix(*address),
+                        condition_suffix(condition)
+                    );
+                }
+            }
+            Command::Watch(address, access, condition) => {
+                self.computer.add_watchpoint(Watchpoint {
+                    address,
+                    access,
+                    condition,
+                });
+                println!("watchpoint set on {}", address);
+            }
+            Command::Unwatch(address) => {
+                if !self.computer.remove_watchpoints(address) {
+                    println!("no watchpoint on {}", address);
+                }
+            }
+            Command::Watchpoints => {
+                for watchpoint in self.computer.watchpoints() {
+                    println!(
+                        "{} {}{}",
+                        access_name(watchpoint.access),
+                        watchpoint.address,
+                        condition_suffix(&watchpoint.condition)
+                    );
+                }
+            }
+            Command::Memory(address, count) => {
+                let end = address.saturating_add(count.min(MEMORY_DISPLAY_LIMIT));
+                for address in address..end {
+                    println!("{:>6}: {}", address, self.computer.read_memory(address));
+                }
+            }
+            Command::Poke(address, value) => {
+                if let Err(error) = self.computer.write_memory(address, value) {
+                    println!("{}", error);
+                }
+            }
+            Command::Registers => {
+                println!("ip: {}", self.computer.instruction_ptr());
+                println!("relative base: {}", self.computer.relative_base());
+                println!("input: {:?}", self.computer.pending_input());
+                println!("output: {:?}", self.computer.pending_output());
+            }
+            Command::Input(values) => {
+                for value in values {
+                    self.computer.provide_input(value);
+                }
+            }
+            Command::Output => {
+                while !self.computer.pending_output().is_empty() {
+                    println!("{}", self.computer.take_output());
+                }
+            }
+            Command::Help => println!("{}", HELP),
+            Command::Quit => {}
+        }
+    }
+
+    /// Runs until anything worth stopping for.
+    fn run(&mut self) {
+        loop {
+            // a stop for a breakpoint or watchpoint can come with output too, and a fault
+            // leaves `last_interrupt` as it was, so check whether output was actually made
+            let pending = self.computer.pending_output().len();
+            let stop = self.computer.execute_until_break();
+            if self.computer.pending_output().len() > pending {
+                self.show_output();
+            }
+            match stop {
+                Ok(Stop::Interrupt(Interrupt::Output)) => {}
+                Ok(Stop::Interrupt(Interrupt::Input)) => {
+                    println!("waiting for input");
+                    break;
+                }
+                Ok(Stop::Interrupt(Interrupt::Halt)) => {
+                    println!("halted");
+                    break;
+                }
+                Ok(Stop::Breakpoint(address)) => {
+                    println!("breakpoint at {}", address);
+                    break;
+                }
+                Ok(Stop::Watchpoint(_)) => {
+                    self.show_watch_hits();
+                    break;
+                }
+                Err(error) => {
+                    println!("{}", error);
+                    break;
+                }
+            }
+        }
+    }
+
+    fn show_output(&self) {
+        if let Some(output) = self.computer.pending_output().back() {
+            println!("output: {}", output);
+        }
+    }
+
+    fn show_watch_hits(&self) {
+        for WatchHit {
+            address,
+            access,
+            value,
+        } in self.computer.watch_hits()
+        {
+            println!(
+                "watchpoint: {} {} = {}",
+                access_name(*access),
+                address,
+                value
+            );
+        }
+    }
+
+    /// Executes a single instruction, returning whether execution can continue.
+    fn step(&mut self) -> bool {
+        let result = self.computer.step();
+        if !self.computer.watch_hits().is_empty() {
+            self.show_watch_hits();
+            return false;
+        }
+        match result {
+            Ok(None) => true,
+            Ok(Some(Interrupt::Output)) => {
+                self.show_output();
+                true
+            }
+            Ok(Some(Interrupt::Input)) => {
+                println!("waiting for input");
+                false
+            }
+            Ok(Some(Interrupt::Halt)) => {
+                println!("halted");
+                false
+            }
+            Err(error) => {
+                println!("{}", error);
+                false
+            }
+        }
+    }
+
+    fn resolve_location(&self, location: &str) -> Option<usize> {
+        location
+            .parse()
+            .ok()
+            .or_else(|| self.source_map.address_of(location))
+    }
+
+    fn label_suffix(&self, address: usize) -> String {
+        match self.source_map.nearest_label(address) {
+            Some((label, 0)) => format!(" <{}>", label),
+            Some((label, offset)) => format!(" <{}+{}>", label, offset),
+            None => String::new(),
+        }
+    }
+
+    fn show_source_line(&self, address: usize) {
+        if let Some((file, line)) = self.source_map.location(address) {
+            let text = self
+                .sources
+                .get(file)
+                .and_then(|lines| lines.get(line - 1))
+                .map(|text| text.trim())
+                .unwrap_or("");
+            println!("  --> {}:{}: {}", file, line, text);
+        }
+    }
+
+    fn show_location(&self) {
+        let ip = self.computer.instruction_ptr();
+        match self.computer.current_instruction() {
+            Ok(instruction) => println!(
+                "{}{}: {}",
+                ip,
+                self.label_suffix(ip),
+                format_instruction(&instruction)
+            ),
+            Err(error) => println!("{}{}: {}", ip, self.label_suffix(ip), error),
+        }
+        self.show_source_line(ip);
+    }
+}
+
+fn access_name(access: Access) -> &'static str {
+    match access {
+        Access::Read => "read",
+        Access::Write => "write",
+    }
+}
+
+fn condition_suffix(condition: &Option<Condition>) -> String {
+    match condition {
+        Some(condition) => format!(" if {}", condition),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command("s"), Ok(Command::Step(1)));
+        assert_eq!(parse_command("step 5"), Ok(Command::Step(5)));
+        assert_eq!(
+            parse_command("b main"),
+            Ok(Command::Break("main".to_string(), None))
+        );
+        assert_eq!(
+            parse_command("b 4 if [10] == 1"),
+            Ok(Command::Break(
+                "4".to_string(),
+                Some("[10] == 1".parse().unwrap())
+            ))
+        );
+        assert_eq!(
+            parse_command("rwatch 386"),
+            Ok(Command::Watch(386, Access::Read, None))
+        );
+        assert!(parse_command("watch 386 when value > 0").is_err());
+        assert_eq!(parse_command("rs 3"), Ok(Command::StepBack(3)));
+        assert_eq!(parse_command("rw 10"), Ok(Command::BackToWrite(10)));
+        assert_eq!(parse_command("x 10 4"), Ok(Command::Memory(10, 4)));
+        assert_eq!(parse_command("poke 3 -7"), Ok(Command::Poke(3, -7)));
+        assert_eq!(
+            parse_command("input 1,2 3"),
+            Ok(Command::Input(vec![1, 2, 3]))
+        );
+        assert!(parse_command("poke 3").is_err());
+        assert!(parse_command("frobnicate").is_err());
+    }
+
+    #[test]
+    fn test_continue_stops_at_breakpoint() {
+        let computer = IntCodeComputer::new(vec![1101, 1, 1, 0, 1101, 2, 2, 0, 99]);
+        let mut debugger = Debugger::new(computer, SourceMap::default());
+        debugger.handle(Command::Break("4".to_string(), None));
+        debugger.handle(Command::Continue);
+        assert_eq!(debugger.computer.instruction_ptr(), 4);
+        assert_eq!(debugger.computer.read_memory(0), 2);
+    }
+
+    #[test]
+    fn test_labels_come_from_source_map() {
+        let assembly =
+            intcode_assembler::assemble_file("test.is", "ADD ^1 ^1 0\nloop: ADD 0 ^1 0\nHALT")
+                .unwrap();
+        let computer = IntCodeComputer::new(assembly.intcode);
+        let mut debugger = Debugger::new(computer, assembly.source_map);
+        assert_eq!(debugger.resolve_location("loop"), Some(4));
+        assert_eq!(debugger.label_suffix(6), " <loop+2>");
+        debugger.handle(Command::Break("loop".to_string(), None));
+        debugger.handle(Command::Continue);
+        assert_eq!(debugger.computer.instruction_ptr(), 4);
+    }
+
+    #[test]
+    fn test_break_on_labels_sharing_an_address() {
+        let assembly =
+            intcode_assembler::assemble_file("test.is", "main:\nloop: ADD ^1 ^1 0\nHALT").unwrap();
+        let computer = IntCodeComputer::new(assembly.intcode);
+        let debugger = Debugger::new(computer, assembly.source_map);
+        assert_eq!(debugger.resolve_location("main"), Some(0));
+        assert_eq!(debugger.resolve_location("loop"), Some(0));
+    }
+
+    #[test]
+    fn test_step_back_to_write() {
+        let computer = IntCodeComputer::new(vec![1101, 1, 1, 0, 1101, 2, 2, 9, 99, 0]);
+        let mut debugger = Debugger::new(computer, SourceMap::default());
+        debugger.handle(Command::Continue);
+        debugger.handle(Command::BackToWrite(0));
+        assert_eq!(debugger.computer.instruction_ptr(), 0);
+        assert_eq!(debugger.computer.read_memory(0), 1101);
+        debugger.handle(Command::Step(2));
+        debugger.handle(Command::StepBack(1));
+        assert_eq!(debugger.computer.instruction_ptr(), 4);
+        assert_eq!(debugger.computer.read_memory(9), 0);
+    }
+
+    #[test]
+    fn test_continue_stops_at_watchpoint() {
+        // counts memory[10] down from 3
+        let computer = IntCodeComputer::new(vec![1001, 10, -1, 10, 1005, 10, 0, 99, 0, 0, 3]);
+        let mut debugger = Debugger::new(computer, SourceMap::default());
+        debugger.handle(parse_command("watch 10 if value == 0").unwrap());
+        debugger.handle(Command::Continue);
+        assert_eq!(debugger.computer.instruction_ptr(), 4);
+        assert_eq!(debugger.computer.read_memory(10), 0);
+        debugger.handle(Command::Unwatch(10));
+        debugger.handle(Command::Continue);
+        assert_eq!(debugger.computer.last_interrupt(), Some(Interrupt::Halt));
+    }
+
+    #[test]
+    fn test_huge_memory_display() {
+        let computer = IntCodeComputer::new(vec![99]);
+        let mut debugger = Debugger::new(computer, SourceMap::default());
+        debugger.handle(parse_command("x 1 18446744073709551615").unwrap());
+        debugger.handle(Command::Memory(usize::MAX, 2));
+    }
+
+    #[test]
+    fn test_poke_out_of_range() {
+        let computer = IntCodeComputer::new(vec![99]);
+        let mut debugger = Debugger::new(computer, SourceMap::default());
+        debugger.handle(parse_command("poke 18446744073709551615 1").unwrap());
+        debugger.handle(parse_command("poke 100000000000000 1").unwrap());
+        debugger.handle(parse_command("poke 3 1").unwrap());
+        assert_eq!(debugger.computer.read_memory(3), 1);
+        assert_eq!(debugger.computer.read_memory(100_000_000_000_000), 0);
+    }
+
+    #[test]
+    fn test_fault_right_after_output() {
+        let computer = IntCodeComputer::new(vec![104, 5, 42]);
+        let mut debugger = Debugger::new(computer, SourceMap::default());
+        debugger.handle(Command::Step(1));
+        debugger.handle(Command::Output);
+        debugger.handle(Command::Continue);
+        assert_eq!(debugger.computer.instruction_ptr(), 2);
+        assert!(debugger.computer.pending_output().is_empty());
+        debugger.handle(Command::Continue);
+        assert_eq!(debugger.computer.instruction_ptr(), 2);
+    }
+}
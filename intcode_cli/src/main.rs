@@ -1,8 +1,25 @@
-use clap::{App, Arg, SubCommand};
-use intcode_assembler::assemble;
-use intcode_computer::{Computer, IntCodeComputer, IntcodeMemoryCellType, IntcodeMemoryType};
-use std::fs::{read_to_string, write};
-use std::io;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use intcode_assembler::{
+    assemble_file, assemble_object, disassemble, link, Assembly, Diagnostic, LinkError, Object,
+    SourceMap,
+};
+use intcode_computer::isa::{InstructionSet, StandardIsa};
+use intcode_computer::profile::Profiler;
+use intcode_computer::trace::{TraceFormat, WriteTracer};
+use intcode_computer::{IntCodeComputer, IntcodeMemoryType};
+use std::fs::{read_to_string, write, File};
+use std::io::{self, BufWriter};
+use std::path::Path;
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+mod debug;
+mod profile;
+mod run;
+
+use debug::Debugger;
+use run::{parse_text, parse_values, InputSource, OutputFormat, RunError};
 
 fn main() {
     let build_command = SubCommand::with_name("build")
@@ -16,14 +33,173 @@ fn main() {
                 .default_value("a.int")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("OBJECT")
+                .short("c")
+                .long("object")
+                .help("Emits an object file for `link` instead of a program"),
+        )
+        .arg(
+            Arg::with_name("MAP")
+                .short("m")
+                .long("map")
+                .conflicts_with("OBJECT")
+                .help("Also writes a source map next to the output file"),
+        )
         .arg(
             Arg::with_name("INPUT")
                 .help("Sets the input file to use")
                 .required(true)
                 .index(1),
         );
+    let link_command = SubCommand::with_name("link")
+        .about("links object files into an intcode program")
+        .arg(
+            Arg::with_name("OUTPUT")
+                .short("o")
+                .long("output")
+                .value_name("FILE")
+                .help("Sets a custom output file")
+                .default_value("a.int")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("INPUT")
+                .help("Sets the object files to link, the first one runs first")
+                .required(true)
+                .multiple(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("MAP")
+                .short("m")
+                .long("map")
+                .help("Also writes a source map next to the output file"),
+        );
     let run_command = SubCommand::with_name("run")
         .about("runs an intcode program")
+        .arg(
+            Arg::with_name("INPUT")
+                .help("Sets the input file to use")
+                .required(true)
+                .index(1),
+        )
+        .args(&input_args())
+        .arg(
+            Arg::with_name("FORMAT")
+                .short("f")
+                .long("format")
+                .value_name("FORMAT")
+                .help("Sets how program output is printed")
+                .possible_values(&["lines", "comma", "ascii"])
+                .default_value("lines")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("MAP")
+                .long("map")
+                .value_name("FILE")
+                .help("Loads a source map to show where addresses came from")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("TRACE")
+                .long("trace")
+                .value_name("FILE")
+                .help("Records every executed instruction to a file")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("TRACE_FORMAT")
+                .long("trace-format")
+                .value_name("FORMAT")
+                .help("Sets how the trace is written [default: log]")
+                .possible_values(&["log", "json"])
+                .requires("TRACE")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("ASCII")
+                .long("ascii")
+                .help("Same as --format ascii, but input is sent to the program as text")
+                .conflicts_with("FORMAT"),
+        )
+        .arg(
+            Arg::with_name("MAX_STEPS")
+                .long("max-steps")
+                .value_name("N")
+                .help("Stops the program with an error after N instructions")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("TIMEOUT")
+                .long("timeout")
+                .value_name("SECONDS")
+                .help("Stops the program with an error once it has run this long")
+                .validator(|seconds| parse_timeout(&seconds).map(|_| ()))
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("CHECKED")
+                .long("checked")
+                .help("Stops the program with an error on arithmetic overflow instead of wrapping"),
+        );
+    let profile_command = SubCommand::with_name("profile")
+        .about("runs an intcode program and reports where it spent its steps")
+        .arg(
+            Arg::with_name("INPUT")
+                .help("Sets the input file to use")
+                .required(true)
+                .index(1),
+        )
+        .args(&input_args())
+        .arg(
+            Arg::with_name("MAP")
+                .long("map")
+                .value_name("FILE")
+                .help("Loads a source map to show where addresses came from")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("TOP")
+                .long("top")
+                .value_name("N")
+                .help("Sets how many addresses and loops to list")
+                .default_value("10")
+                .takes_value(true),
+        );
+    let debug_command = SubCommand::with_name("debug")
+        .about("steps through an intcode program interactively")
+        .arg(
+            Arg::with_name("INPUT")
+                .help("Sets the input file to use")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("ASSEMBLY")
+                .short("a")
+                .long("asm")
+                .help("Treats the input file as assembly, enabling label breakpoints"),
+        )
+        .arg(
+            Arg::with_name("MAP")
+                .long("map")
+                .value_name("FILE")
+                .help("Loads a source map to show where addresses came from")
+                .takes_value(true)
+                .conflicts_with("ASSEMBLY"),
+        );
+    let disasm_command = SubCommand::with_name("disasm")
+        .about("disassembles an intcode program into assembly")
+        .arg(
+            Arg::with_name("OUTPUT")
+                .short("o")
+                .long("output")
+                .value_name("FILE")
+                .help("Writes the assembly to a file instead of stdout")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("INPUT")
                 .help("Sets the input file to use")
@@ -35,29 +211,198 @@ fn main() {
         .author(env!("CARGO_PKG_AUTHORS"))
         .about(env!("CARGO_PKG_DESCRIPTION"))
         .subcommand(build_command)
+        .subcommand(link_command)
         .subcommand(run_command)
+        .subcommand(profile_command)
+        .subcommand(debug_command)
+        .subcommand(disasm_command)
         .get_matches();
     if let Some(subcommand) = matches.subcommand_name() {
         match subcommand {
             "build" => {
                 let matches = matches.subcommand_matches("build").unwrap();
                 let input_file = matches.value_of("INPUT").unwrap();
+                if matches.is_present("OBJECT") {
+                    let output_file = match matches.occurrences_of("OUTPUT") {
+                        0 => "a.o",
+                        _ => matches.value_of("OUTPUT").unwrap(),
+                    };
+                    build_object(input_file, output_file, &StandardIsa);
+                } else {
+                    let output_file = matches.value_of("OUTPUT").unwrap();
+                    build(input_file, output_file, matches.is_present("MAP"));
+                }
+            }
+            "link" => {
+                let matches = matches.subcommand_matches("link").unwrap();
+                let input_files: Vec<&str> = matches.values_of("INPUT").unwrap().collect();
                 let output_file = matches.value_of("OUTPUT").unwrap();
-                build(input_file, output_file);
+                link_objects(&input_files, output_file, matches.is_present("MAP"));
             }
             "run" => {
                 let matches = matches.subcommand_matches("run").unwrap();
                 let input_file = matches.value_of("INPUT").unwrap();
-                run(input_file);
+                // --ascii is -f ascii with input read as text
+                let (input, format) = if matches.is_present("ASCII") {
+                    (ascii_input_source(matches), "ascii")
+                } else {
+                    (input_source(matches), matches.value_of("FORMAT").unwrap())
+                };
+                let format = OutputFormat::from_name(format).unwrap();
+                let mut computer = IntCodeComputer::new(read_intcode(input_file));
+                let source_map = matches.value_of("MAP").map(read_source_map);
+                computer.set_step_limit(matches.value_of("MAX_STEPS").map(|steps| {
+                    steps.parse().unwrap_or_else(|_| {
+                        eprintln!("--max-steps expects a number");
+                        process::exit(1);
+                    })
+                }));
+                computer.set_timeout(
+                    matches
+                        .value_of("TIMEOUT")
+                        .map(|seconds| parse_timeout(seconds).unwrap()),
+                );
+                computer.set_overflow_checks(matches.is_present("CHECKED"));
+                let tracer = matches.value_of("TRACE").map(|trace_file| {
+                    let format = match matches.value_of("TRACE_FORMAT") {
+                        Some("json") => TraceFormat::JsonLines,
+                        _ => TraceFormat::Log,
+                    };
+                    let file = File::create(trace_file).expect("Invalid trace file");
+                    let tracer =
+                        Arc::new(Mutex::new(WriteTracer::new(BufWriter::new(file), format)));
+                    computer.set_tracer(Box::new(tracer.clone()));
+                    tracer
+                });
+                let result = run::run(computer, input, format, io::stdout().lock());
+                if let Some(Ok(tracer)) = tracer.map(Arc::try_unwrap) {
+                    if let Err(error) = tracer.into_inner().unwrap().into_inner() {
+                        eprintln!("failed to write trace: {}", error);
+                    }
+                }
+                if let Err(error) = result {
+                    report_run_error(error, source_map.as_ref());
+                }
+            }
+            "profile" => {
+                let matches = matches.subcommand_matches("profile").unwrap();
+                let input_file = matches.value_of("INPUT").unwrap();
+                let top = matches
+                    .value_of("TOP")
+                    .unwrap()
+                    .parse()
+                    .unwrap_or_else(|_| {
+                        eprintln!("--top expects a number");
+                        process::exit(1);
+                    });
+                let source_map = matches.value_of("MAP").map(read_source_map);
+                let mut computer = IntCodeComputer::new(read_intcode(input_file));
+                let profiler = Arc::new(Mutex::new(Profiler::new()));
+                computer.set_tracer(Box::new(profiler.clone()));
+                let result = run::run(
+                    computer,
+                    input_source(matches),
+                    OutputFormat::Lines,
+                    io::sink(),
+                );
+                let profile = profiler.lock().unwrap().profile().clone();
+                print!("{}", profile::report(&profile, source_map.as_ref(), top));
+                if let Err(error) = result {
+                    report_run_error(error, source_map.as_ref());
+                }
+            }
+            "debug" => {
+                let matches = matches.subcommand_matches("debug").unwrap();
+                let input_file = matches.value_of("INPUT").unwrap();
+                let (intcode, source_map) = if matches.is_present("ASSEMBLY") {
+                    let assembly = assemble_or_exit(input_file);
+                    (assembly.intcode, assembly.source_map)
+                } else {
+                    let source_map = matches.value_of("MAP").map(read_source_map);
+                    (read_intcode(input_file), source_map.unwrap_or_default())
+                };
+                Debugger::new(IntCodeComputer::new(intcode), source_map).repl();
+            }
+            "disasm" => {
+                let matches = matches.subcommand_matches("disasm").unwrap();
+                let input_file = matches.value_of("INPUT").unwrap();
+                let assembly = disassemble(&read_intcode(input_file));
+                match matches.value_of("OUTPUT") {
+                    Some(output_file) => {
+                        if let Err(error) = write(output_file, assembly) {
+                            eprintln!("{}", error);
+                        }
+                    }
+                    None => print!("{}", assembly),
+                }
             }
             _ => {}
         }
     }
 }
 
-fn build(input_file: &str, output_file: &str) {
+fn build(input_file: &str, output_file: &str, with_map: bool) {
+    let assembly = assemble_or_exit(input_file);
+    write_intcode(assembly.intcode, output_file);
+    if with_map {
+        write_source_map(&assembly.source_map, output_file);
+    }
+}
+
+fn build_object(input_file: &str, output_file: &str, instruction_set: &dyn InstructionSet) {
     let assembly = read_to_string(input_file).expect("Invalid input file");
-    let intcode = assemble(&assembly);
+    let object =
+        assemble_object(input_file, &assembly, instruction_set).unwrap_or_else(|diagnostics| {
+            report_and_exit(&diagnostics, input_file, &assembly);
+        });
+    if let Err(error) = write(output_file, object.to_string()) {
+        eprintln!("{}", error);
+    }
+}
+
+fn link_objects(input_files: &[&str], output_file: &str, with_map: bool) {
+    let objects: Vec<Object> = input_files
+        .iter()
+        .map(|input_file| {
+            let object = read_to_string(input_file).expect("Invalid input file");
+            object.parse().unwrap_or_else(|error| {
+                eprintln!("{}: {}", input_file, error);
+                process::exit(1);
+            })
+        })
+        .collect();
+    let assembly = link(&objects).unwrap_or_else(|errors| {
+        for error in errors.iter() {
+            let module = match error {
+                LinkError::UndefinedSymbol { module, .. }
+                | LinkError::AmbiguousSymbol { module, .. } => *module,
+            };
+            eprintln!("{}: {}", input_files[module], error);
+        }
+        process::exit(1);
+    });
+    write_intcode(assembly.intcode, output_file);
+    if with_map {
+        write_source_map(&assembly.source_map, output_file);
+    }
+}
+
+fn write_source_map(source_map: &SourceMap, output_file: &str) {
+    let map_file = Path::new(output_file).with_extension("map");
+    if let Err(error) = write(map_file, source_map.to_string()) {
+        eprintln!("{}", error);
+    }
+}
+
+fn read_source_map(map_file: &str) -> SourceMap {
+    let source_map = read_to_string(map_file).expect("Invalid source map file");
+    source_map.parse().unwrap_or_else(|error| {
+        eprintln!("{}: {}", map_file, error);
+        process::exit(1);
+    })
+}
+
+fn write_intcode(intcode: IntcodeMemoryType, output_file: &str) {
     let intcode_strs: Vec<_> = intcode.into_iter().map(|i| i.to_string()).collect();
     let intcode_str = intcode_strs.join(",");
 
@@ -67,28 +412,94 @@ fn build(input_file: &str, output_file: &str) {
     }
 }
 
-fn run(input_file: &str) {
-    let intcode_str = read_to_string(input_file).expect("Invalid input file");
-    let intcode: IntcodeMemoryType = intcode_str.split(',').map(|s| s.parse().unwrap()).collect();
-    let output_handler = |output| println!("{}", output);
-    let computer = IntCodeComputer::new(intcode, &output_handler);
-    let mut str_buffer = String::new();
-    let mut int_buffer: IntcodeMemoryType = Vec::new();
-    while computer.execute() {
-        while int_buffer.is_empty() {
-            match io::stdin().read_line(&mut str_buffer) {
-                Ok(_) => {
-                    for maybe_int in str_buffer.lines() {
-                        if let Some(input) = maybe_int.parse::<IntcodeMemoryCellType>().ok() {
-                            int_buffer.push(input);
-                        }
-                    }
-                }
-                Err(error) => eprint!("{}", error),
-            }
-        }
-        for input in int_buffer.drain(..) {
-            computer.provide_input(input);
-        }
+fn assemble_or_exit(input_file: &str) -> Assembly {
+    let assembly = read_to_string(input_file).expect("Invalid input file");
+    assemble_file(input_file, &assembly).unwrap_or_else(|diagnostics| {
+        report_and_exit(&diagnostics, input_file, &assembly);
+    })
+}
+
+fn report_and_exit(diagnostics: &[Diagnostic], input_file: &str, source: &str) -> ! {
+    for diagnostic in diagnostics.iter() {
+        // diagnostics may point into included files
+        let included;
+        let source = if diagnostic.file == input_file {
+            source
+        } else {
+            included = read_to_string(&diagnostic.file).unwrap_or_default();
+            &included
+        };
+        eprintln!("{}", diagnostic.render(source));
     }
+    process::exit(1);
+}
+
+fn input_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("VALUES")
+            .short("i")
+            .long("input")
+            .value_name("VALUES")
+            .help("Provides program input inline, e.g. 1,2,3")
+            .takes_value(true)
+            .conflicts_with("VALUES_FILE"),
+        Arg::with_name("VALUES_FILE")
+            .long("input-file")
+            .value_name("FILE")
+            .help("Reads program input from a file of integers")
+            .takes_value(true),
+    ]
+}
+
+fn input_source(matches: &ArgMatches) -> InputSource {
+    if let Some(values) = matches.value_of("VALUES") {
+        InputSource::values(parse_or_exit(values))
+    } else if let Some(values_file) = matches.value_of("VALUES_FILE") {
+        let values = read_to_string(values_file).expect("Invalid input values file");
+        InputSource::values(parse_or_exit(&values))
+    } else {
+        InputSource::stdin()
+    }
+}
+
+fn ascii_input_source(matches: &ArgMatches) -> InputSource {
+    let text = if let Some(values) = matches.value_of("VALUES") {
+        values.to_string()
+    } else if let Some(values_file) = matches.value_of("VALUES_FILE") {
+        read_to_string(values_file).expect("Invalid input values file")
+    } else {
+        return InputSource::stdin_ascii();
+    };
+    InputSource::values(parse_text(&text).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(1);
+    }))
+}
+
+fn report_run_error(error: RunError, source_map: Option<&SourceMap>) -> ! {
+    eprintln!("{}", error);
+    if let (RunError::Fault(fault), Some(source_map)) = (&error, source_map) {
+        eprintln!("  at {}", source_map.describe(fault.instruction_ptr()));
+    }
+    process::exit(1);
+}
+
+fn parse_timeout(seconds: &str) -> Result<Duration, String> {
+    seconds
+        .parse()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| format!("'{}' is not a valid number of seconds", seconds))
+}
+
+fn read_intcode(input_file: &str) -> IntcodeMemoryType {
+    let intcode_str = read_to_string(input_file).expect("Invalid input file");
+    parse_or_exit(&intcode_str)
+}
+
+fn parse_or_exit(values: &str) -> IntcodeMemoryType {
+    parse_values(values).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(1);
+    })
 }
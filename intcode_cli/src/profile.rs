@@ -0,0 +1,98 @@
+use intcode_assembler::SourceMap;
+use intcode_computer::profile::Profile;
+
+fn percent(count: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 * 100.0 / total as f64
+    }
+}
+
+pub fn report(profile: &Profile, source_map: Option<&SourceMap>, top: usize) -> String {
+    let describe = |address: usize| match source_map {
+        Some(source_map) => source_map.describe(address),
+        None => String::new(),
+    };
+    let mut result = format!("steps: {}\n", profile.steps);
+    result.push_str(&format!(
+        "blocked on input: {} time(s), {:.3}s\n",
+        profile.input_waits,
+        profile.input_wait_time.as_secs_f64()
+    ));
+
+    result.push_str(&format!(
+        "\n{:<20} {:>12} {:>7}\n",
+        "operation", "count", "%"
+    ));
+    for (operation, count) in profile.operations() {
+        result.push_str(&format!(
+            "{:<20} {:>12} {:>6.1}%\n",
+            format!("{:?}", operation),
+            count,
+            percent(count, profile.steps)
+        ));
+    }
+
+    result.push_str(&format!(
+        "\n{:>8} {:>12} {:>7}  location\n",
+        "address", "count", "%"
+    ));
+    for (address, count) in profile.hottest_addresses(top) {
+        let line = format!(
+            "{:>8} {:>12} {:>6.1}%  {}",
+            address,
+            count,
+            percent(count, profile.steps),
+            describe(address)
+        );
+        result.push_str(line.trim_end());
+        result.push('\n');
+    }
+
+    result.push_str(&format!("\n{:>8} {:>12}  location\n", "loop", "iterations"));
+    for (address, count) in profile.hottest_loops(top) {
+        let line = format!("{:>8} {:>12}  {}", address, count, describe(address));
+        result.push_str(line.trim_end());
+        result.push('\n');
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intcode_computer::operations::Operation;
+
+    #[test]
+    fn test_report() {
+        let mut profile = Profile {
+            steps: 4,
+            ..Profile::default()
+        };
+        profile.by_address.insert(0, 3);
+        profile.by_address.insert(3, 1);
+        profile.by_operation.insert(Operation::Add, 3);
+        profile.by_operation.insert(Operation::Halt, 1);
+        profile.back_edges.insert(0, 2);
+        let mut source_map = SourceMap::default();
+        source_map.labels.insert(0, vec!["main".to_string()]);
+        assert_eq!(
+            report(&profile, Some(&source_map), 1),
+            "\
+steps: 4
+blocked on input: 0 time(s), 0.000s
+
+operation                   count       %
+Add                             3   75.0%
+Halt                            1   25.0%
+
+ address        count       %  location
+       0            3   75.0%  <main>
+
+    loop   iterations  location
+       0            2  <main>
+"
+        );
+    }
+}
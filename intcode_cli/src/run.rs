@@ -0,0 +1,196 @@
+use intcode_computer::ascii;
+use intcode_computer::{Computer, IntCodeComputer, IntcodeError, IntcodeMemoryCellType, Interrupt};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+pub struct InputSource {
+    values: VecDeque<IntcodeMemoryCellType>,
+    from_stdin: bool,
+    // stdin lines are sent as text rather than parsed as numbers
+    ascii: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OutputFormat {
+    Lines,
+    Comma,
+    Ascii,
+}
+
+impl OutputFormat {
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        match name {
+            "lines" => Some(Self::Lines),
+            "comma" => Some(Self::Comma),
+            "ascii" => Some(Self::Ascii),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RunError {
+    Fault(IntcodeError),
+    InputExhausted,
+    Io(io::Error),
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fault(error) => write!(f, "{}", error),
+            Self::InputExhausted => write!(f, "program requested input but none is left"),
+            Self::Io(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl From<IntcodeError> for RunError {
+    fn from(error: IntcodeError) -> Self {
+        Self::Fault(error)
+    }
+}
+
+impl From<io::Error> for RunError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+pub fn parse_values(values: &str) -> Result<Vec<IntcodeMemoryCellType>, String> {
+    values
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| format!("'{}' is not an integer", value))
+        })
+        .collect()
+}
+
+pub fn parse_text(text: &str) -> Result<Vec<IntcodeMemoryCellType>, String> {
+    ascii::encode(text).ok_or_else(|| "input must be ASCII".to_string())
+}
+
+impl InputSource {
+    pub fn stdin() -> InputSource {
+        InputSource {
+            values: VecDeque::new(),
+            from_stdin: true,
+            ascii: false,
+        }
+    }
+
+    pub fn stdin_ascii() -> InputSource {
+        InputSource {
+            ascii: true,
+            ..InputSource::stdin()
+        }
+    }
+
+    pub fn values(values: Vec<IntcodeMemoryCellType>) -> InputSource {
+        InputSource {
+            values: values.into(),
+            from_stdin: false,
+            ascii: false,
+        }
+    }
+
+    fn next(&mut self) -> Result<IntcodeMemoryCellType, RunError> {
+        while self.values.is_empty() {
+            if !self.from_stdin {
+                return Err(RunError::InputExhausted);
+            }
+            let mut line = String::new();
+            if io::stdin().lock().read_line(&mut line)? == 0 {
+                return Err(RunError::InputExhausted);
+            }
+            if self.ascii && !line.ends_with('\n') {
+                line.push('\n');
+            }
+            let values = if self.ascii {
+                parse_text(&line)
+            } else {
+                parse_values(&line)
+            };
+            match values {
+                Ok(values) => self.values.extend(values),
+                Err(error) => eprintln!("{}", error),
+            }
+        }
+        Ok(self.values.pop_front().unwrap())
+    }
+}
+
+pub fn run<W: Write>(
+    mut computer: IntCodeComputer,
+    mut input: InputSource,
+    format: OutputFormat,
+    mut out: W,
+) -> Result<(), RunError> {
+    let mut outputs: Vec<String> = Vec::new();
+    let result = execute(&mut computer, &mut input, format, &mut out, &mut outputs);
+    // comma separated output is only written at the end, so don't lose it to a fault
+    if format == OutputFormat::Comma && (result.is_ok() || !outputs.is_empty()) {
+        writeln!(out, "{}", outputs.join(","))?;
+    }
+    out.flush()?;
+    result
+}
+
+fn execute<W: Write>(
+    computer: &mut IntCodeComputer,
+    input: &mut InputSource,
+    format: OutputFormat,
+    out: &mut W,
+    outputs: &mut Vec<String>,
+) -> Result<(), RunError> {
+    // so a value that isn't ASCII gets a line of its own
+    let mut line_start = true;
+    loop {
+        match computer.execute()? {
+            Interrupt::Input => {
+                out.flush()?;
+                computer.provide_input(input.next()?);
+            }
+            Interrupt::Output => {
+                let output = computer.take_output();
+                match format {
+                    OutputFormat::Lines => writeln!(out, "{}", output)?,
+                    OutputFormat::Comma => outputs.push(output.to_string()),
+                    OutputFormat::Ascii => match ascii::to_char(output) {
+                        Some(c) => {
+                            write!(out, "{}", c)?;
+                            line_start = c == '\n';
+                        }
+                        None => {
+                            if !line_start {
+                                writeln!(out)?;
+                            }
+                            writeln!(out, "{}", output)?;
+                            line_start = true;
+                        }
+                    },
+                }
+            }
+            Interrupt::Halt => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_values() {
+        assert_eq!(parse_values("1,2,3\n"), Ok(vec![1, 2, 3]));
+        assert_eq!(parse_values("4 -5\n6"), Ok(vec![4, -5, 6]));
+        assert_eq!(parse_values(""), Ok(vec![]));
+        assert!(parse_values("1,x").is_err());
+        assert_eq!(parse_text("A\n"), Ok(vec![65, 10]));
+        assert!(parse_text("→").is_err());
+    }
+}
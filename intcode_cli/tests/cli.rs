@@ -0,0 +1,71 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+fn program(name: &str, intcode: &str) -> PathBuf {
+    let path =
+        std::env::temp_dir().join(format!("intcode_cli_{}_{}.int", name, std::process::id()));
+    fs::write(&path, intcode).unwrap();
+    path
+}
+
+fn intcode_cli(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_intcode_cli"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_run_without_trace() {
+    let path = program("no_trace", "104,5,99");
+    let output = intcode_cli(&["run", path.to_str().unwrap()]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "5\n");
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_trace_format_requires_trace() {
+    let path = program("trace_format", "104,5,99");
+    let output = intcode_cli(&["run", "--trace-format", "json", path.to_str().unwrap()]);
+    assert!(!output.status.success());
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_timeout_out_of_range() {
+    let path = program("timeout", "104,5,99");
+    for timeout in &["1e30", "-1", "nan"] {
+        let timeout = format!("--timeout={}", timeout);
+        let output = intcode_cli(&["run", &timeout, path.to_str().unwrap()]);
+        assert_eq!(output.status.code(), Some(1), "{:?}", output);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("not a valid number of seconds"));
+    }
+    let output = intcode_cli(&["run", "--timeout", "2.5", path.to_str().unwrap()]);
+    assert!(output.status.success(), "{:?}", output);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_comma_output_before_a_fault() {
+    let path = program("comma_fault", "104,72,104,105,104,10,3,0,99");
+    let output = intcode_cli(&["run", path.to_str().unwrap(), "-f", "comma", "-i", ""]);
+    assert!(!output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "72,105,10\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("none is left"));
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_ascii_answer_on_its_own_line() {
+    // prints "Hi\n", echoes one character, then answers 1000
+    let path = program("ascii", "104,72,104,105,104,10,3,13,4,13,104,1000,99,0");
+    let output = intcode_cli(&["run", "--ascii", "-i", "A", path.to_str().unwrap()]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Hi\nA\n1000\n");
+    let output = intcode_cli(&["run", "-f", "ascii", "-i", "65", path.to_str().unwrap()]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Hi\nA\n1000\n");
+    fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,78 @@
+// Compares the decoded instruction cache against decoding every step. Run with `cargo bench`.
+use intcode_computer::prelude::*;
+use std::time::{Duration, Instant};
+
+const RUNS: u32 = 5;
+
+fn read_program(source: &str) -> IntcodeMemoryType {
+    source
+        .trim()
+        .split(',')
+        .map(|value| value.parse().unwrap())
+        .collect()
+}
+
+fn boost(program: &[IntcodeMemoryCellType], cached: bool) -> IntcodeMemoryCellType {
+    let mut computer = IntCodeComputer::new(program.to_vec());
+    computer.set_decode_cache(cached);
+    computer.provide_input(2);
+    let mut output = 0;
+    execute! { computer,
+        output { output = computer.take_output() }
+    }
+    output
+}
+
+// plays the whole game by keeping the paddle under the ball
+fn breakout(program: &[IntcodeMemoryCellType], cached: bool) -> IntcodeMemoryCellType {
+    let mut game = program.to_vec();
+    game[0] = 2;
+    let mut computer = IntCodeComputer::new(game);
+    computer.set_decode_cache(cached);
+    let (mut ball, mut paddle, mut score): (IntcodeMemoryCellType, _, _) = (0, 0, 0);
+    let mut tile = Vec::new();
+    execute! { computer,
+        input { computer.provide_input((ball - paddle).signum()) },
+        output {
+            tile.push(computer.take_output());
+            if let [x, y, id] = tile[..] {
+                match (x, y, id) {
+                    (-1, 0, value) => score = value,
+                    (x, _, 3) => paddle = x,
+                    (x, _, 4) => ball = x,
+                    _ => {}
+                }
+                tile.clear();
+            }
+        }
+    }
+    score
+}
+
+fn bench(name: &str, run: impl Fn(bool) -> IntcodeMemoryCellType) {
+    let time = |cached| {
+        let expected = run(cached);
+        let start = Instant::now();
+        for _ in 0..RUNS {
+            assert_eq!(run(cached), expected);
+        }
+        start.elapsed() / RUNS
+    };
+    let uncached = time(false);
+    let cached = time(true);
+    let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    println!(
+        "{:<10} decode every step {:>8.2}ms   cached {:>8.2}ms   {:.2}x",
+        name,
+        millis(uncached),
+        millis(cached),
+        uncached.as_secs_f64() / cached.as_secs_f64()
+    );
+}
+
+fn main() {
+    let day9 = read_program(include_str!("../../advent2019/src/day9_input.txt"));
+    let day13 = read_program(include_str!("../../advent2019/src/day13_input.txt"));
+    bench("day 9", |cached| boost(&day9, cached));
+    bench("day 13", |cached| breakout(&day13, cached));
+}
@@ -0,0 +1,126 @@
+use super::memory::{DenseMemory, Memory};
+use super::{Computer, IntCodeComputer, IntcodeError, IntcodeMemoryCellType, Interrupt};
+
+/// The character an output value stands for, if it is ASCII.
+pub fn to_char(value: IntcodeMemoryCellType) -> Option<char> {
+    if (0..128).contains(&value) {
+        Some(value as u8 as char)
+    } else {
+        None
+    }
+}
+
+/// Input cells spelling out `text`, or `None` if it isn't all ASCII.
+pub fn encode(text: &str) -> Option<Vec<IntcodeMemoryCellType>> {
+    if !text.is_ascii() {
+        return None;
+    }
+    Some(text.bytes().map(IntcodeMemoryCellType::from).collect())
+}
+
+/// Everything a program printed until it asked for input or halted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AsciiOutput {
+    /// Text split on newlines, ending with any text after the last newline.
+    pub lines: Vec<String>,
+    /// The last output that wasn't ASCII, which is how these programs report their answer.
+    pub answer: Option<IntcodeMemoryCellType>,
+    pub halted: bool,
+}
+
+/// Talks to programs that read and write text one character per cell, like the day 17 robot.
+pub struct AsciiIo<M: Memory = DenseMemory> {
+    computer: IntCodeComputer<M>,
+}
+
+impl<M: Memory<Cell = IntcodeMemoryCellType>> AsciiIo<M> {
+    pub fn new(computer: IntCodeComputer<M>) -> AsciiIo<M> {
+        AsciiIo { computer }
+    }
+
+    pub fn computer(&mut self) -> &mut IntCodeComputer<M> {
+        &mut self.computer
+    }
+
+    pub fn into_inner(self) -> IntCodeComputer<M> {
+        self.computer
+    }
+
+    /// Queues `text` as input. Panics if it isn't ASCII.
+    pub fn send(&mut self, text: &str) {
+        let cells = encode(text).expect("intcode programs only read ASCII");
+        for cell in cells {
+            self.computer.provide_input(cell);
+        }
+    }
+
+    /// Queues `line` followed by a newline. Panics if it isn't ASCII.
+    pub fn send_line(&mut self, line: &str) {
+        self.send(line);
+        self.send("\n");
+    }
+
+    /// Runs until the program needs more input than was sent, or halts.
+    pub fn run(&mut self) -> Result<AsciiOutput, IntcodeError> {
+        let mut output = AsciiOutput::default();
+        let mut text = String::new();
+        loop {
+            match self.computer.execute()? {
+                Interrupt::Output => {
+                    let value = self.computer.take_output();
+                    match to_char(value) {
+                        Some(c) => text.push(c),
+                        None => output.answer = Some(value),
+                    }
+                }
+                Interrupt::Input => break,
+                Interrupt::Halt => {
+                    output.halted = true;
+                    break;
+                }
+            }
+        }
+        output.lines = text.split('\n').map(String::from).collect();
+        if text.is_empty() || text.ends_with('\n') {
+            output.lines.pop();
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode("A,1\n"), Some(vec![65, 44, 49, 10]));
+        assert_eq!(encode("é"), None);
+        assert_eq!(to_char(10), Some('\n'));
+        assert_eq!(to_char(128), None);
+    }
+
+    #[test]
+    fn test_conversation() {
+        // prints "?\n", then echoes one character followed by a newline and 1000
+        let program = vec![104, 63, 104, 10, 3, 15, 4, 15, 104, 10, 104, 1000, 99, 0, 0, 0];
+        let mut ascii = AsciiIo::new(IntCodeComputer::new(program));
+        assert_eq!(
+            ascii.run(),
+            Ok(AsciiOutput {
+                lines: vec!["?".to_string()],
+                answer: None,
+                halted: false,
+            })
+        );
+        ascii.send("x");
+        assert_eq!(
+            ascii.run(),
+            Ok(AsciiOutput {
+                lines: vec!["x".to_string()],
+                answer: Some(1000),
+                halted: true,
+            })
+        );
+    }
+}
@@ -0,0 +1,234 @@
+use super::cell::Cell;
+use super::memory::Memory;
+use super::{Computer, IntCodeComputer, IntcodeError, IntcodeMemoryCellType, Interrupt};
+use futures_core::Stream;
+use futures_sink::Sink;
+use std::error::Error;
+use std::fmt;
+use std::future::{poll_fn, Future};
+use std::pin::{pin, Pin};
+use std::task::{Context, Poll};
+
+// long stretches without I/O would otherwise hold up every other task on the executor
+const STEPS_PER_YIELD: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsyncError<E, C = IntcodeMemoryCellType> {
+    Fault(IntcodeError<C>),
+    /// The program asked for input after the stream ended.
+    InputExhausted,
+    Sink(E),
+}
+
+impl<E: fmt::Display, C: Cell> fmt::Display for AsyncError<E, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fault(error) => write!(f, "{}", error),
+            Self::InputExhausted => write!(f, "program requested input but the stream ended"),
+            Self::Sink(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display, C: Cell> Error for AsyncError<E, C> {}
+
+impl<E, C> From<IntcodeError<C>> for AsyncError<E, C> {
+    fn from(error: IntcodeError<C>) -> Self {
+        Self::Fault(error)
+    }
+}
+
+/// Returns `Pending` once, so the executor gets to run something else.
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// Runs the computer until it halts, taking input from `input` whenever it asks for some and
+/// sending every output to `output`. Output is flushed before waiting for input, so whoever is on
+/// the other end sees everything the program printed before it asked.
+pub async fn execute_async<M, S, K>(
+    computer: &mut IntCodeComputer<M>,
+    input: S,
+    output: K,
+) -> Result<(), AsyncError<K::Error, M::Cell>>
+where
+    M: Memory,
+    S: Stream<Item = M::Cell>,
+    K: Sink<M::Cell>,
+{
+    let mut input = pin!(input);
+    let mut output = pin!(output);
+    let mut steps = 0;
+    loop {
+        let interrupt = match computer.step()? {
+            Some(interrupt) => interrupt,
+            None => {
+                steps += 1;
+                if steps % STEPS_PER_YIELD == 0 {
+                    YieldNow(false).await;
+                }
+                continue;
+            }
+        };
+        match interrupt {
+            Interrupt::Input => {
+                poll_fn(|cx| output.as_mut().poll_flush(cx))
+                    .await
+                    .map_err(AsyncError::Sink)?;
+                match poll_fn(|cx| input.as_mut().poll_next(cx)).await {
+                    Some(value) => computer.provide_input(value),
+                    None => return Err(AsyncError::InputExhausted),
+                }
+            }
+            Interrupt::Output => {
+                poll_fn(|cx| output.as_mut().poll_ready(cx))
+                    .await
+                    .map_err(AsyncError::Sink)?;
+                output
+                    .as_mut()
+                    .start_send(computer.take_output())
+                    .map_err(AsyncError::Sink)?;
+            }
+            Interrupt::Halt => {
+                return poll_fn(|cx| output.as_mut().poll_flush(cx))
+                    .await
+                    .map_err(AsyncError::Sink);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::convert::Infallible;
+    use std::sync::Arc;
+    use std::task::{Wake, Waker};
+    use std::thread::{self, Thread};
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    /// Makes the reader wait once before every value, like input arriving over a network.
+    struct SlowInput {
+        values: VecDeque<IntcodeMemoryCellType>,
+        ready: bool,
+    }
+
+    impl Stream for SlowInput {
+        type Item = IntcodeMemoryCellType;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            if !self.ready {
+                self.ready = true;
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            self.ready = false;
+            Poll::Ready(self.values.pop_front())
+        }
+    }
+
+    /// Records what was sent and when it was flushed.
+    #[derive(Default)]
+    struct Output {
+        sent: Vec<IntcodeMemoryCellType>,
+        flushed: usize,
+    }
+
+    impl Sink<IntcodeMemoryCellType> for &mut Output {
+        type Error = Infallible;
+
+        fn poll_ready(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn start_send(
+            mut self: Pin<&mut Self>,
+            item: IntcodeMemoryCellType,
+        ) -> Result<(), Infallible> {
+            self.sent.push(item);
+            Ok(())
+        }
+
+        fn poll_flush(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Result<(), Infallible>> {
+            self.flushed = self.sent.len();
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            self.poll_flush(cx)
+        }
+    }
+
+    #[test]
+    fn test_execute_async() {
+        // outputs 1, then echoes two inputs doubled
+        let program = vec![104, 1, 3, 13, 1002, 13, 2, 13, 4, 13, 1105, 1, 2, 0];
+        let mut computer = IntCodeComputer::new(program);
+        let input = SlowInput {
+            values: vec![3, 4].into(),
+            ready: false,
+        };
+        let mut output = Output::default();
+        let result = block_on(execute_async(&mut computer, input, &mut output));
+        assert_eq!(result, Err(AsyncError::InputExhausted));
+        assert_eq!(output.sent, vec![1, 6, 8]);
+        assert_eq!(output.flushed, 3);
+    }
+
+    #[test]
+    fn test_long_computation_yields() {
+        // counts memory[9] down from 30000
+        let program = vec![1001, 9, -1, 9, 1005, 9, 0, 99, 0, 30000];
+        let mut computer = IntCodeComputer::new(program);
+        let mut output = Output::default();
+        let mut future = pin!(execute_async(
+            &mut computer,
+            SlowInput {
+                values: VecDeque::new(),
+                ready: false,
+            },
+            &mut output,
+        ));
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut pending = 0;
+        while future.as_mut().poll(&mut cx).is_pending() {
+            pending += 1;
+        }
+        assert_eq!(pending, 6);
+    }
+}
@@ -0,0 +1,305 @@
+use super::cell::Cell;
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Neg};
+use std::str::FromStr;
+
+// the largest power of 10 that fits in a digit, for converting to and from decimal
+const DECIMAL_BASE: u32 = 1_000_000_000;
+const DECIMAL_DIGITS: usize = 9;
+
+/// An integer that grows as needed, so arithmetic on it never overflows.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    // base 2^32, least significant first, without trailing zeros, so zero is empty
+    digits: Vec<u32>,
+}
+
+impl BigInt {
+    fn from_parts(negative: bool, mut digits: Vec<u32>) -> BigInt {
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        BigInt {
+            negative: negative && !digits.is_empty(),
+            digits,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+}
+
+fn compare_magnitudes(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0;
+    for i in 0..a.len().max(b.len()) {
+        let sum = u64::from(a.get(i).copied().unwrap_or(0))
+            + u64::from(b.get(i).copied().unwrap_or(0))
+            + carry;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+    result.push(carry as u32);
+    result
+}
+
+// `a` must be at least as large as `b`
+fn subtract_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = 0;
+    for (i, digit) in a.iter().enumerate() {
+        let difference = i64::from(*digit) - i64::from(b.get(i).copied().unwrap_or(0)) - borrow;
+        borrow = if difference < 0 { 1 } else { 0 };
+        result.push((difference + (borrow << 32)) as u32);
+    }
+    result
+}
+
+fn multiply_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = vec![0u32; a.len() + b.len()];
+    for (i, x) in a.iter().enumerate() {
+        let mut carry = 0;
+        for (j, y) in b.iter().enumerate() {
+            let product = u64::from(*x) * u64::from(*y) + u64::from(result[i + j]) + carry;
+            result[i + j] = product as u32;
+            carry = product >> 32;
+        }
+        result[i + b.len()] = carry as u32;
+    }
+    result
+}
+
+// divides in place, returning the remainder
+fn divide_magnitude(digits: &mut [u32], divisor: u32) -> u32 {
+    let mut remainder = 0u64;
+    for digit in digits.iter_mut().rev() {
+        let value = (remainder << 32) | u64::from(*digit);
+        *digit = (value / u64::from(divisor)) as u32;
+        remainder = value % u64::from(divisor);
+    }
+    remainder as u32
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> Self {
+        let magnitude = value.unsigned_abs();
+        BigInt::from_parts(value < 0, vec![magnitude as u32, (magnitude >> 32) as u32])
+    }
+}
+
+impl Add for BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::from_parts(self.negative, add_magnitudes(&self.digits, &other.digits));
+        }
+        match compare_magnitudes(&self.digits, &other.digits) {
+            Ordering::Less => BigInt::from_parts(
+                other.negative,
+                subtract_magnitudes(&other.digits, &self.digits),
+            ),
+            _ => BigInt::from_parts(
+                self.negative,
+                subtract_magnitudes(&self.digits, &other.digits),
+            ),
+        }
+    }
+}
+
+impl Mul for BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: BigInt) -> BigInt {
+        BigInt::from_parts(
+            self.negative != other.negative,
+            multiply_magnitudes(&self.digits, &other.digits),
+        )
+    }
+}
+
+impl Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::from_parts(!self.negative, self.digits)
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare_magnitudes(&self.digits, &other.digits),
+            (true, true) => compare_magnitudes(&other.digits, &self.digits),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut digits = self.digits.clone();
+        let mut chunks = Vec::new();
+        while !digits.is_empty() {
+            chunks.push(divide_magnitude(&mut digits, DECIMAL_BASE));
+            while digits.last() == Some(&0) {
+                digits.pop();
+            }
+        }
+        let mut text = match chunks.pop() {
+            Some(chunk) => chunk.to_string(),
+            None => "0".to_string(),
+        };
+        for chunk in chunks.iter().rev() {
+            text.push_str(&format!("{:0width$}", chunk, width = DECIMAL_DIGITS));
+        }
+        f.pad_integral(!self.is_negative(), "", &text)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseBigIntError;
+
+impl fmt::Display for ParseBigIntError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid integer")
+    }
+}
+
+impl std::error::Error for ParseBigIntError {}
+
+impl FromStr for BigInt {
+    type Err = ParseBigIntError;
+
+    fn from_str(source: &str) -> Result<BigInt, ParseBigIntError> {
+        let (negative, decimal) = match source.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, source.strip_prefix('+').unwrap_or(source)),
+        };
+        if decimal.is_empty() || !decimal.bytes().all(|c| c.is_ascii_digit()) {
+            return Err(ParseBigIntError);
+        }
+        let mut digits: Vec<u32> = Vec::new();
+        for c in decimal.bytes() {
+            let mut carry = u64::from(c - b'0');
+            for digit in digits.iter_mut() {
+                let value = u64::from(*digit) * 10 + carry;
+                *digit = value as u32;
+                carry = value >> 32;
+            }
+            if carry > 0 {
+                digits.push(carry as u32);
+            }
+        }
+        Ok(BigInt::from_parts(negative, digits))
+    }
+}
+
+impl Cell for BigInt {
+    fn to_i64(&self) -> Option<i64> {
+        if self.digits.len() > 2 {
+            return None;
+        }
+        let magnitude = self
+            .digits
+            .iter()
+            .rev()
+            .fold(0i128, |value, digit| (value << 32) | i128::from(*digit));
+        let value = if self.negative { -magnitude } else { magnitude };
+        value.to_i64()
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(self.clone() + other.clone())
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        Some(self.clone() * other.clone())
+    }
+
+    fn wrapping_add(&self, other: &Self) -> Self {
+        self.clone() + other.clone()
+    }
+
+    fn wrapping_mul(&self, other: &Self) -> Self {
+        self.clone() * other.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(value: &str) -> BigInt {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        for value in &[
+            "0",
+            "-1",
+            "4294967296",
+            "-170141183460469231731687303715884105728",
+        ] {
+            assert_eq!(big(value).to_string(), *value);
+        }
+        assert_eq!(big("-0"), BigInt::default());
+        assert_eq!(big("+007").to_string(), "7");
+        assert_eq!(format!("{:>4}", big("-5")), "  -5");
+        assert_eq!(format!("{:+}", big("-5")), "-5");
+        assert_eq!(format!("{:+}", big("5")), "+5");
+        assert_eq!(format!("{:05}", big("-5")), "-0005");
+        assert_eq!(format!("{:+05}", big("0")), "+0000");
+        assert!("".parse::<BigInt>().is_err());
+        assert!("1e3".parse::<BigInt>().is_err());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let max = BigInt::from(i64::MAX);
+        assert_eq!(
+            (max.clone() + max.clone()).to_string(),
+            "18446744073709551614"
+        );
+        assert_eq!(
+            (max.clone() * max.clone()).to_string(),
+            "85070591730234615847396907784232501249"
+        );
+        assert_eq!(big("100") + big("-142"), big("-42"));
+        assert_eq!(big("-100") + big("142"), big("42"));
+        assert_eq!(big("4294967296") + big("-4294967296"), BigInt::default());
+        assert_eq!(big("-3") * big("7"), big("-21"));
+        assert_eq!(-big("5"), big("-5"));
+    }
+
+    #[test]
+    fn test_ordering_and_conversion() {
+        assert!(big("-5") < big("-4"));
+        assert!(big("-5") < big("3"));
+        assert!(big("4294967296") > big("4294967295"));
+        assert_eq!(BigInt::from(i64::MIN).to_i64(), Some(i64::MIN));
+        assert_eq!(big("9223372036854775808").to_i64(), None);
+        assert_eq!(big("-9223372036854775809").to_i64(), None);
+    }
+}
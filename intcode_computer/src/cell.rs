@@ -0,0 +1,60 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// An integer a computer's memory can be made of. `Default` has to be zero.
+pub trait Cell: Clone + Default + Ord + fmt::Debug + fmt::Display + FromStr + From<i64> {
+    /// The value as an `i64`, if it fits, which opcodes and addresses have to.
+    fn to_i64(&self) -> Option<i64>;
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+    fn wrapping_add(&self, other: &Self) -> Self;
+    fn wrapping_mul(&self, other: &Self) -> Self;
+}
+
+impl Cell for i64 {
+    fn to_i64(&self) -> Option<i64> {
+        Some(*self)
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        i64::checked_add(*self, *other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        i64::checked_mul(*self, *other)
+    }
+
+    fn wrapping_add(&self, other: &Self) -> Self {
+        i64::wrapping_add(*self, *other)
+    }
+
+    fn wrapping_mul(&self, other: &Self) -> Self {
+        i64::wrapping_mul(*self, *other)
+    }
+}
+
+impl Cell for i128 {
+    fn to_i64(&self) -> Option<i64> {
+        if (i64::MIN as i128..=i64::MAX as i128).contains(self) {
+            Some(*self as i64)
+        } else {
+            None
+        }
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        i128::checked_add(*self, *other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        i128::checked_mul(*self, *other)
+    }
+
+    fn wrapping_add(&self, other: &Self) -> Self {
+        i128::wrapping_add(*self, *other)
+    }
+
+    fn wrapping_mul(&self, other: &Self) -> Self {
+        i128::wrapping_mul(*self, *other)
+    }
+}
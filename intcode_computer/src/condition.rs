@@ -0,0 +1,204 @@
+use super::memory::Memory;
+use super::{IntCodeComputer, IntcodeMemoryCellType};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    Constant(IntcodeMemoryCellType),
+    /// `[N]`, the value in memory at address N
+    Memory(usize),
+    /// `rb`
+    RelativeBase,
+    /// `ip`
+    InstructionPtr,
+    /// `value`, the value a watchpoint saw being read or written, 0 anywhere else
+    Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+/// A boolean expression over the state of a computer, such as `[386] != 0 && rb > 100`.
+///
+/// It is a list of comparisons joined by `&&` and `||`, where `&&` binds tighter. There are no
+/// parentheses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    source: String,
+    // any of these must hold, each of which holds if all of its comparisons do
+    alternatives: Vec<Vec<(Operand, Comparison, Operand)>>,
+}
+
+impl Operand {
+    fn evaluate<M: Memory>(self, computer: &IntCodeComputer<M>, value: &M::Cell) -> M::Cell {
+        match self {
+            Operand::Constant(constant) => M::Cell::from(constant),
+            Operand::Memory(address) => computer.read_memory(address),
+            Operand::RelativeBase => computer.relative_base(),
+            Operand::InstructionPtr => M::Cell::from(computer.instruction_ptr() as i64),
+            Operand::Value => value.clone(),
+        }
+    }
+}
+
+impl Comparison {
+    fn holds<C: Ord>(self, left: C, right: C) -> bool {
+        match self {
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterOrEqual => left >= right,
+        }
+    }
+}
+
+impl Condition {
+    pub fn evaluate<M: Memory>(&self, computer: &IntCodeComputer<M>, value: &M::Cell) -> bool {
+        self.alternatives.iter().any(|comparisons| {
+            comparisons.iter().all(|(left, comparison, right)| {
+                comparison.holds(
+                    left.evaluate(computer, value),
+                    right.evaluate(computer, value),
+                )
+            })
+        })
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        let mut token = c.to_string();
+        if c.is_whitespace() {
+            continue;
+        } else if c.is_ascii_alphanumeric() || c == '-' {
+            while let Some(next) = chars.peek().filter(|next| next.is_ascii_alphanumeric()) {
+                token.push(*next);
+                chars.next();
+            }
+        } else if "=!<>&|".contains(c) {
+            if let Some(next) = chars.peek().filter(|next| "=&|".contains(**next)) {
+                token.push(*next);
+                chars.next();
+            }
+        } else if c != '[' && c != ']' {
+            return Err(format!("unexpected '{}'", c));
+        }
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn parse_operand<'a>(tokens: &mut impl Iterator<Item = &'a String>) -> Result<Operand, String> {
+    let token = tokens.next().ok_or("missing operand")?;
+    match token.as_str() {
+        "rb" => Ok(Operand::RelativeBase),
+        "ip" => Ok(Operand::InstructionPtr),
+        "value" => Ok(Operand::Value),
+        "[" => {
+            let address = tokens.next().ok_or("missing address")?;
+            let address = address
+                .parse()
+                .map_err(|_| format!("'{}' is not a valid address", address))?;
+            match tokens.next().map(String::as_str) {
+                Some("]") => Ok(Operand::Memory(address)),
+                _ => Err("missing ']'".to_string()),
+            }
+        }
+        constant => constant
+            .parse()
+            .map(Operand::Constant)
+            .map_err(|_| format!("unexpected '{}'", constant)),
+    }
+}
+
+fn parse_comparison(token: Option<&String>) -> Result<Comparison, String> {
+    match token.map(String::as_str) {
+        Some("==") => Ok(Comparison::Equal),
+        Some("!=") => Ok(Comparison::NotEqual),
+        Some("<") => Ok(Comparison::Less),
+        Some("<=") => Ok(Comparison::LessOrEqual),
+        Some(">") => Ok(Comparison::Greater),
+        Some(">=") => Ok(Comparison::GreaterOrEqual),
+        Some(other) => Err(format!("expected a comparison, found '{}'", other)),
+        None => Err("missing comparison".to_string()),
+    }
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Condition, String> {
+        let tokens = tokenize(source)?;
+        let mut tokens = tokens.iter();
+        let mut alternatives = vec![Vec::new()];
+        loop {
+            let left = parse_operand(&mut tokens)?;
+            let comparison = parse_comparison(tokens.next())?;
+            let right = parse_operand(&mut tokens)?;
+            alternatives
+                .last_mut()
+                .unwrap()
+                .push((left, comparison, right));
+            match tokens.next().map(String::as_str) {
+                None => break,
+                Some("&&") => {}
+                Some("||") => alternatives.push(Vec::new()),
+                Some(other) => return Err(format!("expected '&&' or '||', found '{}'", other)),
+            }
+        }
+        Ok(Condition {
+            source: source.trim().to_string(),
+            alternatives,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate() {
+        let mut computer = IntCodeComputer::new(vec![5, 0, 7]);
+        computer.write_memory(1, -3).unwrap();
+        let holds = |condition: &str, value| {
+            condition
+                .parse::<Condition>()
+                .unwrap()
+                .evaluate(&computer, &value)
+        };
+        assert!(holds("[0] == 5", 0));
+        assert!(holds("[1]<0&&[2]>=7", 0));
+        assert!(!holds("[0] == 5 && rb != 0", 0));
+        assert!(holds("[0] == 4 || ip == 0 && value > [2]", 8));
+        assert!(!holds("[0] == 4 || ip == 0 && value > [2]", 7));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!("".parse::<Condition>().is_err());
+        assert!("[3 == 1".parse::<Condition>().is_err());
+        assert!("rb = 1".parse::<Condition>().is_err());
+        assert!("rb == 1 &&".parse::<Condition>().is_err());
+        assert!("rb == 1 2".parse::<Condition>().is_err());
+        assert!("sp == 1".parse::<Condition>().is_err());
+    }
+}
@@ -0,0 +1,76 @@
+use super::instruction::Instruction;
+use super::operations::Operation;
+use super::parameter::Parameter;
+
+/// An `Instruction` that fits in a fixed size, so it can be cached and cloned cheaply.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct DecodedInstruction<C> {
+    pub operation: Operation,
+    pub parameters: [Parameter<C>; 3],
+}
+
+impl<C: Clone> DecodedInstruction<C> {
+    pub fn parameters(&self) -> &[Parameter<C>] {
+        &self.parameters[..self.operation.parameter_count()]
+    }
+
+    pub fn into_instruction(self) -> Instruction<C> {
+        Instruction {
+            operation: self.operation,
+            parameters: self.parameters().to_vec(),
+        }
+    }
+}
+
+const MAX_INSTRUCTION_LENGTH: usize = 4;
+
+/// Decoded instructions by address. Programs patch themselves, so every write to memory has to
+/// go through `invalidate`.
+#[derive(Debug, Clone)]
+pub(crate) struct DecodeCache<C> {
+    enabled: bool,
+    entries: Vec<Option<DecodedInstruction<C>>>,
+}
+
+impl<C> Default for DecodeCache<C> {
+    fn default() -> Self {
+        DecodeCache {
+            enabled: true,
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<C: Clone> DecodeCache<C> {
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.clear();
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn get(&self, address: usize) -> Option<DecodedInstruction<C>> {
+        self.entries.get(address).cloned().flatten()
+    }
+
+    pub fn insert(&mut self, address: usize, instruction: DecodedInstruction<C>) {
+        if !self.enabled {
+            return;
+        }
+        if address >= self.entries.len() {
+            self.entries.resize(address + 1, None);
+        }
+        self.entries[address] = Some(instruction);
+    }
+
+    /// Forgets every instruction that `address` could be a part of.
+    pub fn invalidate(&mut self, address: usize) {
+        let start = address.saturating_sub(MAX_INSTRUCTION_LENGTH - 1);
+        let end = (address + 1).min(self.entries.len());
+        for entry in self.entries.iter_mut().take(end).skip(start) {
+            *entry = None;
+        }
+    }
+}
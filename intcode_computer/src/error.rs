@@ -0,0 +1,177 @@
+use super::cell::Cell;
+use super::operations::DecodeError;
+use super::IntcodeMemoryCellType;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum IntcodeError<C = IntcodeMemoryCellType> {
+    UnknownOpcode {
+        instruction_ptr: usize,
+        opcode: C,
+    },
+    UnknownParameterMode {
+        instruction_ptr: usize,
+        opcode: C,
+        mode: C,
+    },
+    ImmediateWrite {
+        instruction_ptr: usize,
+        opcode: C,
+    },
+    NegativeAddress {
+        instruction_ptr: usize,
+        opcode: C,
+        address: C,
+    },
+    /// An address too large to be stored anywhere.
+    AddressTooLarge {
+        instruction_ptr: usize,
+        opcode: C,
+        address: C,
+    },
+    TruncatedInstruction {
+        instruction_ptr: usize,
+        opcode: C,
+    },
+    StepLimitExceeded {
+        instruction_ptr: usize,
+        opcode: C,
+        steps: u64,
+    },
+    TimedOut {
+        instruction_ptr: usize,
+        opcode: C,
+    },
+    MemoryLimitExceeded {
+        instruction_ptr: usize,
+        opcode: C,
+        address: usize,
+    },
+    /// Only raised with overflow checks on, otherwise arithmetic wraps around.
+    Overflow {
+        instruction_ptr: usize,
+        opcode: C,
+    },
+    /// An extension operation that has no result for its inputs, like a division by zero.
+    InvalidOperands {
+        instruction_ptr: usize,
+        opcode: C,
+    },
+}
+
+impl<C: Cell> IntcodeError<C> {
+    pub(crate) fn decode(instruction_ptr: usize, opcode: C, error: DecodeError) -> IntcodeError<C> {
+        match error {
+            DecodeError::UnknownOpcode(_) => IntcodeError::UnknownOpcode {
+                instruction_ptr,
+                opcode,
+            },
+            DecodeError::UnknownParameterMode(mode) => IntcodeError::UnknownParameterMode {
+                instruction_ptr,
+                opcode,
+                mode: C::from(mode),
+            },
+        }
+    }
+
+    pub fn instruction_ptr(&self) -> usize {
+        match *self {
+            Self::UnknownOpcode {
+                instruction_ptr, ..
+            }
+            | Self::UnknownParameterMode {
+                instruction_ptr, ..
+            }
+            | Self::ImmediateWrite {
+                instruction_ptr, ..
+            }
+            | Self::NegativeAddress {
+                instruction_ptr, ..
+            }
+            | Self::AddressTooLarge {
+                instruction_ptr, ..
+            }
+            | Self::TruncatedInstruction {
+                instruction_ptr, ..
+            }
+            | Self::StepLimitExceeded {
+                instruction_ptr, ..
+            }
+            | Self::TimedOut {
+                instruction_ptr, ..
+            }
+            | Self::MemoryLimitExceeded {
+                instruction_ptr, ..
+            }
+            | Self::Overflow {
+                instruction_ptr, ..
+            }
+            | Self::InvalidOperands {
+                instruction_ptr, ..
+            } => instruction_ptr,
+        }
+    }
+
+    pub fn opcode(&self) -> C {
+        match self {
+            Self::UnknownOpcode { opcode, .. }
+            | Self::UnknownParameterMode { opcode, .. }
+            | Self::ImmediateWrite { opcode, .. }
+            | Self::NegativeAddress { opcode, .. }
+            | Self::AddressTooLarge { opcode, .. }
+            | Self::TruncatedInstruction { opcode, .. }
+            | Self::StepLimitExceeded { opcode, .. }
+            | Self::TimedOut { opcode, .. }
+            | Self::MemoryLimitExceeded { opcode, .. }
+            | Self::Overflow { opcode, .. }
+            | Self::InvalidOperands { opcode, .. } => opcode.clone(),
+        }
+    }
+}
+
+impl<C: Cell> fmt::Display for IntcodeError<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "fault at address {} (opcode {}): ",
+            self.instruction_ptr(),
+            self.opcode()
+        )?;
+        match self {
+            Self::UnknownOpcode { .. } => write!(f, "unknown opcode"),
+            Self::UnknownParameterMode { mode, .. } => {
+                write!(f, "unknown parameter mode {}", mode)
+            }
+            Self::ImmediateWrite { .. } => write!(f, "attempted to write to an immediate value"),
+            Self::NegativeAddress { address, .. } => {
+                write!(f, "attempted to access negative address {}", address)
+            }
+            Self::AddressTooLarge { address, .. } => {
+                write!(
+                    f,
+                    "attempted to access address {}, which is too large",
+                    address
+                )
+            }
+            Self::TruncatedInstruction { .. } => {
+                write!(f, "instruction runs past the end of memory")
+            }
+            Self::StepLimitExceeded { steps, .. } => {
+                write!(f, "step limit reached after {} steps", steps)
+            }
+            Self::TimedOut { .. } => write!(f, "ran out of time"),
+            Self::MemoryLimitExceeded { address, .. } => {
+                write!(
+                    f,
+                    "attempted to write to address {} past the memory limit",
+                    address
+                )
+            }
+            Self::Overflow { .. } => write!(f, "arithmetic overflow"),
+            Self::InvalidOperands { .. } => write!(f, "operation has no result for its operands"),
+        }
+    }
+}
+
+impl<C: Cell> Error for IntcodeError<C> {}
@@ -0,0 +1,58 @@
+use super::operations::Operation;
+use super::Interrupt;
+use std::collections::VecDeque;
+
+/// What one executed instruction changed, enough to put everything back.
+#[derive(Debug, Clone)]
+pub(crate) struct Change<C> {
+    pub operation: Operation,
+    pub instruction_ptr: usize,
+    pub relative_base: C,
+    pub interrupted: Option<Interrupt>,
+    // (address, value before the write)
+    pub writes: Vec<(usize, C)>,
+}
+
+/// An undo log of the most recent instructions, oldest first.
+#[derive(Debug, Clone)]
+pub(crate) struct History<C> {
+    limit: usize,
+    changes: VecDeque<Change<C>>,
+}
+
+impl<C> History<C> {
+    pub fn new(limit: usize) -> History<C> {
+        History {
+            limit,
+            changes: VecDeque::new(),
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn push(&mut self, change: Change<C>) {
+        if self.changes.len() == self.limit {
+            self.changes.pop_front();
+        }
+        self.changes.push_back(change);
+    }
+
+    pub fn pop(&mut self) -> Option<Change<C>> {
+        self.changes.pop_back()
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// How many steps back the last write to `address` happened, counting the write itself.
+    pub fn steps_since_write(&self, address: usize) -> Option<usize> {
+        self.changes
+            .iter()
+            .rev()
+            .position(|change| change.writes.iter().any(|(a, _)| *a == address))
+            .map(|position| position + 1)
+    }
+}
@@ -3,9 +3,9 @@ use super::parameter::{Parameter, ParameterMode};
 use super::{IntcodeMemoryCellType, IntcodeMemoryType};
 
 #[derive(Debug)]
-pub struct Instruction {
+pub struct Instruction<C = IntcodeMemoryCellType> {
     pub operation: Operation,
-    pub parameters: Vec<Parameter>,
+    pub parameters: Vec<Parameter<C>>,
 }
 
 impl Into<IntcodeMemoryType> for Instruction {
@@ -0,0 +1,340 @@
+use super::memory::Memory;
+use super::pipe::Pipe;
+use super::threaded::SyncPipe;
+use super::{Computer, IntCodeComputer, IntcodeError, IntcodeMemoryCellType, Interrupt};
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+use std::iter::FusedIterator;
+
+/// Where a computer's input comes from. Returning `None` pauses the run until more is available.
+pub trait InputProvider<C = IntcodeMemoryCellType> {
+    fn next_input(&mut self) -> Option<C>;
+}
+
+/// Where a computer's output goes.
+pub trait OutputSink<C = IntcodeMemoryCellType> {
+    fn send_output(&mut self, value: C);
+}
+
+impl<C, F: FnMut() -> Option<C>> InputProvider<C> for F {
+    fn next_input(&mut self) -> Option<C> {
+        self()
+    }
+}
+
+/// Provides each of `values` in turn.
+pub fn from_iter<C, I: IntoIterator<Item = C>>(values: I) -> impl InputProvider<C> {
+    let mut values = values.into_iter();
+    move || values.next()
+}
+
+impl<C> InputProvider<C> for VecDeque<C> {
+    fn next_input(&mut self) -> Option<C> {
+        self.pop_front()
+    }
+}
+
+impl InputProvider for &Pipe {
+    fn next_input(&mut self) -> Option<IntcodeMemoryCellType> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.receive())
+        }
+    }
+}
+
+impl InputProvider for &SyncPipe {
+    fn next_input(&mut self) -> Option<IntcodeMemoryCellType> {
+        self.receive()
+    }
+}
+
+/// Reads whitespace or comma separated values, a line at a time. Anything that isn't a number
+/// ends the input.
+impl InputProvider for io::StdinLock<'_> {
+    fn next_input(&mut self) -> Option<IntcodeMemoryCellType> {
+        let mut value = Vec::new();
+        loop {
+            let (done, used) = {
+                let buffer = self.fill_buf().ok()?;
+                if buffer.is_empty() {
+                    (true, 0)
+                } else {
+                    let separator = buffer
+                        .iter()
+                        .position(|c| *c == b',' || c.is_ascii_whitespace());
+                    match separator {
+                        Some(index) => {
+                            value.extend_from_slice(&buffer[..index]);
+                            (!value.is_empty(), index + 1)
+                        }
+                        None => {
+                            value.extend_from_slice(buffer);
+                            (false, buffer.len())
+                        }
+                    }
+                }
+            };
+            self.consume(used);
+            if done {
+                break;
+            }
+        }
+        std::str::from_utf8(&value).ok()?.parse().ok()
+    }
+}
+
+impl<C, F: FnMut(C)> OutputSink<C> for F {
+    fn send_output(&mut self, value: C) {
+        self(value)
+    }
+}
+
+impl<C> OutputSink<C> for Vec<C> {
+    fn send_output(&mut self, value: C) {
+        self.push(value);
+    }
+}
+
+impl<C> OutputSink<C> for VecDeque<C> {
+    fn send_output(&mut self, value: C) {
+        self.push_back(value);
+    }
+}
+
+impl OutputSink for &Pipe {
+    fn send_output(&mut self, value: IntcodeMemoryCellType) {
+        self.send(value);
+    }
+}
+
+impl OutputSink for &SyncPipe {
+    fn send_output(&mut self, value: IntcodeMemoryCellType) {
+        self.send(value);
+    }
+}
+
+/// Writes each value on its own line. Panics if stdout can't be written to, like `println!`.
+impl OutputSink for io::StdoutLock<'_> {
+    fn send_output(&mut self, value: IntcodeMemoryCellType) {
+        writeln!(self, "{}", value).expect("failed writing to stdout");
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RunSummary {
+    pub steps: u64,
+    pub inputs: usize,
+    pub outputs: usize,
+    /// Whether the program halted, rather than stopping because it ran out of input.
+    pub halted: bool,
+}
+
+impl<M: Memory> IntCodeComputer<M> {
+    /// Runs until the program halts, or asks for input that `input` doesn't have. In that case
+    /// the computer is left waiting, and calling this again picks up where it stopped.
+    pub fn run_with<I, O>(
+        &mut self,
+        input: &mut I,
+        output: &mut O,
+    ) -> Result<RunSummary, IntcodeError<M::Cell>>
+    where
+        I: InputProvider<M::Cell> + ?Sized,
+        O: OutputSink<M::Cell> + ?Sized,
+    {
+        let steps = self.steps();
+        let mut summary = RunSummary::default();
+        loop {
+            match self.execute()? {
+                Interrupt::Input => match input.next_input() {
+                    Some(value) => {
+                        self.provide_input(value);
+                        summary.inputs += 1;
+                    }
+                    None => break,
+                },
+                Interrupt::Output => {
+                    output.send_output(self.take_output());
+                    summary.outputs += 1;
+                }
+                Interrupt::Halt => {
+                    summary.halted = true;
+                    break;
+                }
+            }
+        }
+        summary.steps = self.steps() - steps;
+        Ok(summary)
+    }
+
+    /// Lazily runs the computer, yielding each output as it's made. Ends when the program halts,
+    /// faults or asks for input that `input` doesn't have.
+    pub fn outputs<I: InputProvider<M::Cell>>(&mut self, input: I) -> Outputs<'_, M, I> {
+        Outputs {
+            computer: self,
+            input,
+            done: false,
+        }
+    }
+
+    /// Like `outputs`, but groups them into frames of `N`, like the `(x, y, tile)` of a screen.
+    pub fn output_chunks<const N: usize, I: InputProvider<M::Cell>>(
+        &mut self,
+        input: I,
+    ) -> OutputChunks<'_, M, I, N> {
+        assert!(N > 0, "frames need at least one value");
+        OutputChunks {
+            outputs: self.outputs(input),
+            remainder: Vec::with_capacity(N),
+        }
+    }
+
+    /// Every output until the program halts or runs out of input.
+    pub fn run_collect<I: InputProvider<M::Cell>>(
+        &mut self,
+        input: I,
+    ) -> Result<Vec<M::Cell>, IntcodeError<M::Cell>> {
+        self.outputs(input).collect()
+    }
+}
+
+pub struct Outputs<'a, M: Memory, I> {
+    computer: &'a mut IntCodeComputer<M>,
+    input: I,
+    done: bool,
+}
+
+impl<M: Memory, I: InputProvider<M::Cell>> Iterator for Outputs<'_, M, I> {
+    type Item = Result<M::Cell, IntcodeError<M::Cell>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            match self.computer.execute() {
+                Ok(Interrupt::Output) => return Some(Ok(self.computer.take_output())),
+                Ok(Interrupt::Input) => match self.input.next_input() {
+                    Some(value) => self.computer.provide_input(value),
+                    None => self.done = true,
+                },
+                Ok(Interrupt::Halt) => self.done = true,
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(error));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<M: Memory, I: InputProvider<M::Cell>> FusedIterator for Outputs<'_, M, I> {}
+
+pub struct OutputChunks<'a, M: Memory, I, const N: usize> {
+    outputs: Outputs<'a, M, I>,
+    remainder: Vec<M::Cell>,
+}
+
+impl<M: Memory, I, const N: usize> OutputChunks<'_, M, I, N> {
+    /// Outputs that didn't make up a whole frame before the computer stopped.
+    pub fn remainder(&self) -> &[M::Cell] {
+        &self.remainder
+    }
+}
+
+impl<M: Memory, I: InputProvider<M::Cell>, const N: usize> Iterator for OutputChunks<'_, M, I, N> {
+    type Item = Result<[M::Cell; N], IntcodeError<M::Cell>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.remainder.len() < N {
+            match self.outputs.next()? {
+                Ok(value) => self.remainder.push(value),
+                Err(error) => return Some(Err(error)),
+            }
+        }
+        let mut values = self.remainder.drain(..);
+        Some(Ok(std::array::from_fn(|_| values.next().unwrap())))
+    }
+}
+
+impl<M: Memory, I: InputProvider<M::Cell>, const N: usize> FusedIterator
+    for OutputChunks<'_, M, I, N>
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // adds up input until it reads a 0, then outputs the total
+    const SUM: [IntcodeMemoryCellType; 17] = [
+        3, 16, 1006, 16, 12, 1, 15, 16, 15, 1105, 1, 0, 4, 15, 99, 0, 0,
+    ];
+
+    #[test]
+    fn test_run_with() {
+        let mut computer = IntCodeComputer::new(SUM.to_vec());
+        let mut outputs = Vec::new();
+        let summary = computer.run_with(&mut from_iter(vec![1, 2]), &mut outputs);
+        assert_eq!(
+            summary,
+            Ok(RunSummary {
+                steps: 8,
+                inputs: 2,
+                outputs: 0,
+                halted: false
+            })
+        );
+
+        let mut sum = 0;
+        let mut input = VecDeque::from(vec![3, 0]);
+        let summary = computer.run_with(&mut input, &mut |value| sum = value);
+        assert_eq!(summary.map(|summary| summary.halted), Ok(true));
+        assert_eq!(sum, 6);
+    }
+
+    #[test]
+    fn test_pipes() {
+        let (input, output) = (Pipe::new(), Pipe::new());
+        input.send(4);
+        input.send(0);
+        let mut computer = IntCodeComputer::new(SUM.to_vec());
+        let summary = computer.run_with(&mut &input, &mut &output).unwrap();
+        assert!(summary.halted);
+        assert_eq!(output.receive(), 4);
+    }
+
+    #[test]
+    fn test_outputs() {
+        // outputs 1, 2, 3, then echoes its input
+        let program = vec![104, 1, 104, 2, 104, 3, 3, 11, 4, 11, 99, 0];
+        let mut computer = IntCodeComputer::new(program.clone());
+        let mut outputs = computer.outputs(from_iter(vec![]));
+        assert_eq!(outputs.next(), Some(Ok(1)));
+        assert_eq!(outputs.next(), Some(Ok(2)));
+        assert_eq!(outputs.by_ref().count(), 1);
+        assert_eq!(computer.run_collect(from_iter(vec![7])), Ok(vec![7]));
+
+        let mut computer = IntCodeComputer::new(program);
+        let mut frames = computer.output_chunks::<2, _>(from_iter(vec![4]));
+        assert_eq!(frames.next(), Some(Ok([1, 2])));
+        assert_eq!(frames.next(), Some(Ok([3, 4])));
+        assert_eq!(frames.next(), None);
+        assert!(frames.remainder().is_empty());
+    }
+
+    #[test]
+    fn test_partial_frame() {
+        let mut computer = IntCodeComputer::new(vec![104, 1, 104, 2, 104, 3, 99]);
+        let mut frames = computer.output_chunks::<2, _>(from_iter(vec![]));
+        assert_eq!(frames.next(), Some(Ok([1, 2])));
+        assert_eq!(frames.next(), None);
+        assert_eq!(frames.remainder(), &[3]);
+
+        let mut computer = IntCodeComputer::new(vec![104, 1, 42]);
+        let frames: Vec<_> = computer.output_chunks::<2, _>(|| None).collect();
+        assert!(matches!(
+            frames[..],
+            [Err(IntcodeError::UnknownOpcode { .. })]
+        ));
+    }
+}
@@ -0,0 +1,173 @@
+use super::operations::{Extension, Operation};
+use super::IntcodeMemoryCellType;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+/// Instructions are at most four cells long, so an extension has room for two inputs besides
+/// where it writes its result.
+pub const MAX_EXTENSION_INPUTS: usize = 2;
+
+/// Which operation each opcode stands for, and what the extensions beyond the standard
+/// operations compute.
+pub trait InstructionSet<C = IntcodeMemoryCellType> {
+    /// The operation for an opcode with its parameter modes taken off.
+    fn operation(&self, code: IntcodeMemoryCellType) -> Option<Operation>;
+
+    /// The result of `extension` for its input values, or `None` if there isn't one, like when
+    /// dividing by zero.
+    fn evaluate(&self, extension: Extension, inputs: &[C]) -> Option<C>;
+
+    /// Every extension, so an assembler can pick up their mnemonics.
+    fn extensions(&self) -> Vec<Extension>;
+}
+
+/// The operations from day 9, which every computer starts out with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StandardIsa;
+
+impl<C> InstructionSet<C> for StandardIsa {
+    fn operation(&self, code: IntcodeMemoryCellType) -> Option<Operation> {
+        Operation::try_from(code).ok()
+    }
+
+    fn evaluate(&self, _extension: Extension, _inputs: &[C]) -> Option<C> {
+        None
+    }
+
+    fn extensions(&self) -> Vec<Extension> {
+        Vec::new()
+    }
+}
+
+type Semantics<C> = Arc<dyn Fn(&[C]) -> Option<C> + Send + Sync>;
+
+/// The standard operations plus any registered extensions.
+#[derive(Clone)]
+pub struct ExtendedIsa<C = IntcodeMemoryCellType> {
+    extensions: BTreeMap<IntcodeMemoryCellType, (Extension, Semantics<C>)>,
+}
+
+impl<C> Default for ExtendedIsa<C> {
+    fn default() -> Self {
+        ExtendedIsa {
+            extensions: BTreeMap::new(),
+        }
+    }
+}
+
+impl<C> ExtendedIsa<C> {
+    pub fn new() -> ExtendedIsa<C> {
+        ExtendedIsa::default()
+    }
+
+    /// Adds an operation that passes the values of its first `inputs` parameters to `semantics`
+    /// and writes the result to the parameter after them.
+    ///
+    /// Panics if `opcode` isn't a free two digit opcode, if `mnemonic` isn't alphanumeric or is
+    /// already taken by another extension, or if there are more than `MAX_EXTENSION_INPUTS`
+    /// inputs. Assemblers match their own mnemonics first, so reusing one of those hides the
+    /// extension from them.
+    pub fn register<F>(
+        &mut self,
+        opcode: IntcodeMemoryCellType,
+        mnemonic: &'static str,
+        inputs: usize,
+        semantics: F,
+    ) -> Extension
+    where
+        F: Fn(&[C]) -> Option<C> + Send + Sync + 'static,
+    {
+        assert!(
+            (1..100).contains(&opcode)
+                && Operation::try_from(opcode).is_err()
+                && !self.extensions.contains_key(&opcode),
+            "opcode {} is not free",
+            opcode
+        );
+        assert!(
+            !mnemonic.is_empty() && mnemonic.chars().all(|c| c.is_ascii_alphanumeric()),
+            "invalid mnemonic '{}'",
+            mnemonic
+        );
+        assert!(
+            self.extensions
+                .values()
+                .all(|(extension, _)| !extension.mnemonic.eq_ignore_ascii_case(mnemonic)),
+            "mnemonic '{}' is already taken",
+            mnemonic
+        );
+        assert!(
+            inputs <= MAX_EXTENSION_INPUTS,
+            "extensions take at most {} inputs",
+            MAX_EXTENSION_INPUTS
+        );
+        let extension = Extension {
+            opcode,
+            mnemonic,
+            inputs,
+        };
+        self.extensions
+            .insert(opcode, (extension, Arc::new(semantics)));
+        extension
+    }
+}
+
+impl<C> InstructionSet<C> for ExtendedIsa<C> {
+    fn operation(&self, code: IntcodeMemoryCellType) -> Option<Operation> {
+        Operation::try_from(code).ok().or_else(|| {
+            self.extensions
+                .get(&code)
+                .map(|(extension, _)| Operation::Extension(*extension))
+        })
+    }
+
+    fn evaluate(&self, extension: Extension, inputs: &[C]) -> Option<C> {
+        let (_, semantics) = self.extensions.get(&extension.opcode)?;
+        semantics(inputs)
+    }
+
+    fn extensions(&self) -> Vec<Extension> {
+        self.extensions
+            .values()
+            .map(|(extension, _)| *extension)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::{DecodeError, OpCode};
+    use crate::parameter::ParameterMode;
+
+    #[test]
+    fn test_extended_decoding() {
+        let mut isa = ExtendedIsa::new();
+        let divide = isa.register(10, "DIV", 2, |x: &[i64]| x[0].checked_div(x[1]));
+        assert_eq!(
+            OpCode::decode(1110, &isa),
+            Ok(OpCode {
+                operation: Operation::Extension(divide),
+                parameter_modes: vec![
+                    ParameterMode::Value,
+                    ParameterMode::Value,
+                    ParameterMode::Pointer
+                ]
+            })
+        );
+        assert_eq!(
+            OpCode::decode::<i64, _>(1110, &StandardIsa),
+            Err(DecodeError::UnknownOpcode(10))
+        );
+        assert_eq!(isa.evaluate(divide, &[7, 2]), Some(3));
+        assert_eq!(isa.evaluate(divide, &[7, 0]), None);
+        assert_eq!(isa.extensions(), vec![divide]);
+    }
+
+    #[test]
+    #[should_panic(expected = "opcode 9 is not free")]
+    fn test_standard_opcodes_are_taken() {
+        ExtendedIsa::new().register(9, "DIV", 2, |x: &[i64]| x[0].checked_div(x[1]));
+    }
+}
@@ -1,240 +1,825 @@
+pub mod ascii;
+pub mod async_io;
+pub mod bigint;
+pub mod cell;
+pub mod condition;
+mod decoded;
+pub mod error;
+mod history;
 pub mod instruction;
+pub mod io;
+pub mod isa;
+pub mod memory;
+pub mod network;
 pub mod operations;
 pub mod parameter;
 pub mod pipe;
 pub mod prelude;
+pub mod profile;
+pub mod snapshot;
 mod sugar;
+pub mod threaded;
+pub mod trace;
+pub mod watch;
 
+use cell::Cell;
+use condition::Condition;
+use decoded::{DecodeCache, DecodedInstruction};
+pub use error::IntcodeError;
+use history::{Change, History};
 use instruction::*;
+use isa::{InstructionSet, StandardIsa};
+use memory::{DenseMemory, Memory};
 use operations::*;
 use parameter::*;
-use std::collections::{HashMap, VecDeque};
+use snapshot::Snapshot;
+use std::collections::{BTreeMap, VecDeque};
+use std::convert::TryFrom;
+use std::mem;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use trace::{TraceEvent, Tracer};
+use watch::{Access, Stop, WatchHit, Watchpoint};
 
 pub trait Computer<MemoryType> {
-    fn execute(&mut self) -> Interrupt;
-    fn step(&mut self);
+    fn execute(&mut self) -> Result<Interrupt, IntcodeError<MemoryType>>;
+    fn step(&mut self) -> Result<Option<Interrupt>, IntcodeError<MemoryType>>;
 }
 
 pub type IntcodeMemoryCellType = i64;
 pub type IntcodeMemoryType = Vec<i64>;
-type InternalMemoryType = HashMap<usize, i64>;
-
-pub struct IntCodeComputer {
-    memory: InternalMemoryType,
+pub struct IntCodeComputer<M: Memory = DenseMemory> {
+    memory: M,
     instruction_ptr: usize,
-    input_buffer: VecDeque<IntcodeMemoryCellType>,
-    output_buffer: VecDeque<IntcodeMemoryCellType>,
+    input_buffer: VecDeque<M::Cell>,
+    output_buffer: VecDeque<M::Cell>,
     interrupted: Option<Interrupt>,
-    relative_base: IntcodeMemoryCellType,
+    relative_base: M::Cell,
+    steps: u64,
+    tracer: Option<Box<dyn Tracer<M::Cell> + Send>>,
+    // writes made by the current instruction, only collected while tracing or watching
+    writes: Vec<(usize, M::Cell)>,
+    decode_cache: DecodeCache<M::Cell>,
+    history: Option<History<M::Cell>>,
+    // old values overwritten by the current instruction, only collected while recording history
+    undo_writes: Vec<(usize, M::Cell)>,
+    breakpoints: BTreeMap<usize, Option<Condition>>,
+    watchpoints: Vec<Watchpoint>,
+    watch_hits: Vec<WatchHit<M::Cell>>,
+    step_limit: Option<u64>,
+    deadline: Option<Instant>,
+    memory_limit: Option<usize>,
+    overflow_checks: bool,
+    instruction_set: Arc<dyn InstructionSet<M::Cell> + Send + Sync>,
 }
 
+/// How many cells a computer may write to unless `set_memory_limit` says otherwise, so a
+/// program can't take all the memory there is.
+pub const DEFAULT_MEMORY_LIMIT: usize = 1 << 24;
+
+// checking the clock every step would slow everything down
+const DEADLINE_CHECK_INTERVAL: u64 = 1024;
+
 impl IntCodeComputer {
     pub fn new(memory: Vec<IntcodeMemoryCellType>) -> IntCodeComputer {
+        IntCodeComputer::with_memory(DenseMemory::from(memory))
+    }
+}
+
+impl<M: Memory> IntCodeComputer<M> {
+    pub fn with_memory(memory: M) -> IntCodeComputer<M> {
         IntCodeComputer {
-            memory: memory.into_iter().enumerate().collect(),
+            memory,
             instruction_ptr: 0,
             input_buffer: VecDeque::new(),
             output_buffer: VecDeque::new(),
             interrupted: None,
-            relative_base: 0,
+            relative_base: M::Cell::default(),
+            steps: 0,
+            tracer: None,
+            writes: Vec::new(),
+            decode_cache: DecodeCache::default(),
+            history: None,
+            undo_writes: Vec::new(),
+            breakpoints: BTreeMap::new(),
+            watchpoints: Vec::new(),
+            watch_hits: Vec::new(),
+            step_limit: None,
+            deadline: None,
+            memory_limit: Some(DEFAULT_MEMORY_LIMIT),
+            overflow_checks: false,
+            instruction_set: Arc::new(StandardIsa),
         }
     }
 
-    pub fn provide_input(&mut self, input: IntcodeMemoryCellType) {
+    /// Caching decoded instructions is on by default; turning it off is mostly useful to compare.
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.decode_cache.set_enabled(enabled);
+    }
+
+    pub fn set_tracer(&mut self, tracer: Box<dyn Tracer<M::Cell> + Send>) {
+        self.tracer = Some(tracer);
+    }
+
+    pub fn take_tracer(&mut self) -> Option<Box<dyn Tracer<M::Cell> + Send>> {
+        self.tracer.take()
+    }
+
+    /// Starts keeping an undo log of the last `limit` instructions so they can be stepped back
+    /// over. A limit of 0 turns the log off.
+    pub fn record_history(&mut self, limit: usize) {
+        self.history = Some(limit).filter(|limit| *limit > 0).map(History::new);
+    }
+
+    /// The number of instructions that can currently be stepped back over.
+    pub fn history_len(&self) -> usize {
+        self.history.as_ref().map_or(0, History::len)
+    }
+
+    /// Undoes up to `count` instructions, returning how many were undone. Input read by an undone
+    /// instruction is put back, output it produced is dropped if it hasn't been taken yet.
+    pub fn step_back(&mut self, count: usize) -> usize {
+        for undone in 0..count {
+            let change = match self.history.as_mut().and_then(History::pop) {
+                Some(change) => change,
+                None => return undone,
+            };
+            if change.operation == Operation::Input {
+                let address = change.writes[0].0;
+                self.input_buffer.push_front(self.memory.read(address));
+            }
+            for (address, value) in change.writes.into_iter().rev() {
+                self.memory
+                    .write(address, value)
+                    .expect("undone writes were in range when they were made");
+                self.decode_cache.invalidate(address);
+            }
+            // output is taken from the front, so if this one is still pending it's at the back,
+            // and if it was taken everything before it was too
+            if change.operation == Operation::Output {
+                self.output_buffer.pop_back();
+            }
+            self.instruction_ptr = change.instruction_ptr;
+            self.relative_base = change.relative_base;
+            self.interrupted = change.interrupted;
+            self.steps -= 1;
+        }
+        count
+    }
+
+    /// Steps back to just before the most recent instruction that wrote to `address`, returning
+    /// how many instructions were undone, or `None` without changing anything if no instruction
+    /// in the history wrote there.
+    pub fn step_back_to_write(&mut self, address: usize) -> Option<usize> {
+        let count = self.history.as_ref()?.steps_since_write(address)?;
+        Some(self.step_back(count))
+    }
+
+    /// Faults with `StepLimitExceeded` instead of executing more than `limit` instructions in
+    /// total, counting from when the computer was created.
+    pub fn set_step_limit(&mut self, limit: Option<u64>) {
+        self.step_limit = limit;
+    }
+
+    /// Faults with `TimedOut` once `timeout` has passed from now. The clock is only checked every
+    /// so often, so the fault can come a little late. A timeout too long to represent never runs
+    /// out.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+    }
+
+    /// Faults with `MemoryLimitExceeded` instead of writing at or past address `limit`, which
+    /// starts out as `DEFAULT_MEMORY_LIMIT`. Without a limit, the memory's own `max_len` still
+    /// applies.
+    pub fn set_memory_limit(&mut self, limit: Option<usize>) {
+        self.memory_limit = limit;
+    }
+
+    /// Faults with `Overflow` when arithmetic, including relative addressing, doesn't fit in a
+    /// cell. Off by default, which lets it wrap around instead.
+    pub fn set_overflow_checks(&mut self, enabled: bool) {
+        self.overflow_checks = enabled;
+    }
+
+    /// Decodes and executes instructions with `instruction_set` instead of the standard one.
+    pub fn set_instruction_set<S>(&mut self, instruction_set: S)
+    where
+        S: InstructionSet<M::Cell> + Send + Sync + 'static,
+    {
+        self.instruction_set = Arc::new(instruction_set);
+        self.decode_cache.clear();
+    }
+
+    /// Makes `execute_until_break` stop when the instruction pointer reaches `address`, if
+    /// `condition` holds at that point.
+    pub fn set_breakpoint(&mut self, address: usize, condition: Option<Condition>) {
+        self.breakpoints.insert(address, condition);
+    }
+
+    pub fn remove_breakpoint(&mut self, address: usize) -> bool {
+        self.breakpoints.remove(&address).is_some()
+    }
+
+    pub fn breakpoints(&self) -> &BTreeMap<usize, Option<Condition>> {
+        &self.breakpoints
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        self.watchpoints.push(watchpoint);
+    }
+
+    /// Removes every watchpoint on `address`, returning whether there were any.
+    pub fn remove_watchpoints(&mut self, address: usize) -> bool {
+        let count = self.watchpoints.len();
+        self.watchpoints
+            .retain(|watchpoint| watchpoint.address != address);
+        self.watchpoints.len() != count
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    /// The watchpoints triggered by the last instruction executed.
+    pub fn watch_hits(&self) -> &[WatchHit<M::Cell>] {
+        &self.watch_hits
+    }
+
+    /// Runs until an interrupt, a watchpoint triggers, or the instruction pointer lands on a
+    /// breakpoint. At least one instruction is always executed, so calling this again carries on
+    /// past a breakpoint. The instruction that stopped at a watchpoint or breakpoint may also
+    /// have produced output, which shows in `last_interrupt`.
+    pub fn execute_until_break(&mut self) -> Result<Stop<M::Cell>, IntcodeError<M::Cell>> {
+        loop {
+            let interrupt = self.step()?;
+            if let Some(hit) = self.watch_hits.first() {
+                return Ok(Stop::Watchpoint(hit.clone()));
+            }
+            if let Some(Interrupt::Input) | Some(Interrupt::Halt) = interrupt {
+                return Ok(Stop::Interrupt(interrupt.unwrap()));
+            }
+            if let Some(condition) = self.breakpoints.get(&self.instruction_ptr) {
+                if condition
+                    .as_ref()
+                    .is_none_or(|c| c.evaluate(self, &M::Cell::default()))
+                {
+                    return Ok(Stop::Breakpoint(self.instruction_ptr));
+                }
+            }
+            if let Some(interrupt) = interrupt {
+                return Ok(Stop::Interrupt(interrupt));
+            }
+        }
+    }
+
+    /// The number of instructions executed so far, not counting reads that waited for input.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn provide_input(&mut self, input: M::Cell) {
         self.input_buffer.push_back(input);
     }
 
-    pub fn take_output(&mut self) -> IntcodeMemoryCellType {
+    pub fn take_output(&mut self) -> M::Cell {
         self.output_buffer.pop_front().unwrap()
     }
 
-    pub fn terminate(mut self) -> IntcodeMemoryType {
-        // TODO: insert blanks
-        let mut sorted_by_address: Vec<_> = self.memory.drain().collect();
-        sorted_by_address.sort_unstable_by_key(|(k, _)| *k);
-        sorted_by_address.drain(..).map(|(_, v)| v).collect()
+    pub fn instruction_ptr(&self) -> usize {
+        self.instruction_ptr
+    }
+
+    pub fn relative_base(&self) -> M::Cell {
+        self.relative_base.clone()
+    }
+
+    pub fn last_interrupt(&self) -> Option<Interrupt> {
+        self.interrupted
+    }
+
+    pub fn pending_input(&self) -> &VecDeque<M::Cell> {
+        &self.input_buffer
+    }
+
+    pub fn pending_output(&self) -> &VecDeque<M::Cell> {
+        &self.output_buffer
+    }
+
+    pub fn read_memory(&self, address: usize) -> M::Cell {
+        self.memory.read(address)
+    }
+
+    /// Faults the same way the program would writing to `address`, without changing anything.
+    pub fn write_memory(
+        &mut self,
+        address: usize,
+        value: M::Cell,
+    ) -> Result<(), IntcodeError<M::Cell>> {
+        self.check_write_address(address)?;
+        self.memory
+            .write(address, value)
+            .expect("write addresses are checked against the memory's max_len");
+        self.decode_cache.invalidate(address);
+        Ok(())
+    }
+
+    pub fn into_memory(self) -> M {
+        self.memory
+    }
+}
+
+impl<C: Cell> IntCodeComputer<DenseMemory<C>> {
+    pub fn terminate(self) -> Vec<C> {
+        self.memory.into_vec()
+    }
+}
+
+impl<M: Memory + Clone> IntCodeComputer<M> {
+    pub fn snapshot(&self) -> Snapshot<M> {
+        Snapshot {
+            memory: self.memory.clone(),
+            instruction_ptr: self.instruction_ptr,
+            relative_base: self.relative_base.clone(),
+            input_buffer: self.input_buffer.iter().cloned().collect(),
+            output_buffer: self.output_buffer.iter().cloned().collect(),
+            interrupted: self.interrupted,
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot<M>) {
+        self.memory = snapshot.memory.clone();
+        self.instruction_ptr = snapshot.instruction_ptr;
+        self.relative_base = snapshot.relative_base.clone();
+        self.input_buffer = snapshot.input_buffer.iter().cloned().collect();
+        self.output_buffer = snapshot.output_buffer.iter().cloned().collect();
+        self.interrupted = snapshot.interrupted;
+        self.decode_cache.clear();
+        if let Some(history) = self.history.as_mut() {
+            *history = History::new(history.limit());
+        }
     }
 }
 
-impl<'a> Computer<IntcodeMemoryCellType> for IntCodeComputer {
-    fn execute(&mut self) -> Interrupt {
-        let memory_len = self.memory.len();
-        while self.instruction_ptr < memory_len {
-            self.step();
-            if let Some(interrupt) = self.interrupted {
-                return interrupt;
+// the tracer stays with the original, a fork starts out untraced
+impl<M: Memory + Clone> Clone for IntCodeComputer<M> {
+    fn clone(&self) -> Self {
+        IntCodeComputer {
+            memory: self.memory.clone(),
+            instruction_ptr: self.instruction_ptr,
+            input_buffer: self.input_buffer.clone(),
+            output_buffer: self.output_buffer.clone(),
+            interrupted: self.interrupted,
+            relative_base: self.relative_base.clone(),
+            steps: self.steps,
+            tracer: None,
+            writes: Vec::new(),
+            decode_cache: self.decode_cache.clone(),
+            history: self.history.clone(),
+            undo_writes: Vec::new(),
+            breakpoints: self.breakpoints.clone(),
+            watchpoints: self.watchpoints.clone(),
+            watch_hits: self.watch_hits.clone(),
+            step_limit: self.step_limit,
+            deadline: self.deadline,
+            memory_limit: self.memory_limit,
+            overflow_checks: self.overflow_checks,
+            instruction_set: self.instruction_set.clone(),
+        }
+    }
+}
+
+impl<M: Memory> Computer<M::Cell> for IntCodeComputer<M> {
+    fn execute(&mut self) -> Result<Interrupt, IntcodeError<M::Cell>> {
+        loop {
+            if let Some(interrupt) = self.step()? {
+                return Ok(interrupt);
             }
         }
-        return Interrupt::Halt;
     }
 
-    fn step(&mut self) {
-        let instruction = Instruction::read(&self.memory, &self.instruction_ptr);
-        self.execute_instruction(instruction);
+    fn step(&mut self) -> Result<Option<Interrupt>, IntcodeError<M::Cell>> {
+        self.interrupted = if self.instruction_ptr < self.memory.len() {
+            self.check_limits()?;
+            let instruction = self.fetch()?;
+            let before = match self.tracer {
+                Some(_) => Some((
+                    self.instruction_ptr,
+                    self.relative_base.clone(),
+                    self.resolve_operands(&instruction),
+                )),
+                None => None,
+            };
+            self.watch_hits.clear();
+            let reads = if self.watching(Access::Read) {
+                self.operand_reads(&instruction)
+            } else {
+                Vec::new()
+            };
+            let operation = instruction.operation;
+            let undo = (
+                self.instruction_ptr,
+                self.relative_base.clone(),
+                self.interrupted,
+            );
+            let interrupt = self.execute_instruction(instruction)?;
+            if interrupt != Some(Interrupt::Input) {
+                self.steps += 1;
+                let writes = mem::take(&mut self.writes);
+                if !self.watchpoints.is_empty() {
+                    self.check_watchpoints(&reads, &writes);
+                }
+                if let Some(history) = self.history.as_mut() {
+                    let (instruction_ptr, relative_base, interrupted) = undo;
+                    history.push(Change {
+                        operation,
+                        instruction_ptr,
+                        relative_base,
+                        interrupted,
+                        writes: mem::take(&mut self.undo_writes),
+                    });
+                }
+                if let Some((instruction_ptr, relative_base, operands)) = before {
+                    let event = TraceEvent {
+                        step: self.steps,
+                        instruction_ptr,
+                        operation,
+                        operands,
+                        writes,
+                        relative_base: Some(self.relative_base.clone())
+                            .filter(|base| *base != relative_base),
+                    };
+                    self.tracer.as_mut().unwrap().record(&event);
+                }
+            }
+            if let (Some(interrupt), Some(tracer)) = (interrupt, self.tracer.as_mut()) {
+                tracer.interrupted(interrupt);
+            }
+            interrupt
+        } else {
+            Some(Interrupt::Halt)
+        };
+        Ok(self.interrupted)
     }
 }
 
+// `$transform` gives `None` if it overflowed
 macro_rules! arith {
     ($transform:expr, $instruction:expr, $computer:ident) => {
-        let storage_index = resolve_pointer($instruction.parameters[2], &$computer.relative_base);
-        let operand1 = resolve_value_in_memory(
-            $instruction.parameters[0],
-            &$computer.memory,
-            &$computer.relative_base,
-        );
-        let operand2 = resolve_value_in_memory(
-            $instruction.parameters[1],
-            &$computer.memory,
-            &$computer.relative_base,
-        );
-        $computer
-            .memory
-            .insert(storage_index, $transform(operand1, operand2));
+        let storage_index = $computer.resolve_write_pointer(&$instruction.parameters[2])?;
+        let operand1 = $computer.resolve_value(&$instruction.parameters[0])?;
+        let operand2 = $computer.resolve_value(&$instruction.parameters[1])?;
+        let result = $transform(operand1, operand2).ok_or_else(|| $computer.overflow())?;
+        $computer.store(storage_index, result);
     };
 }
 
-impl IntCodeComputer {
-    fn execute_instruction(&mut self, instruction: Instruction) {
+impl<M: Memory> IntCodeComputer<M> {
+    fn execute_instruction(
+        &mut self,
+        instruction: DecodedInstruction<M::Cell>,
+    ) -> Result<Option<Interrupt>, IntcodeError<M::Cell>> {
         match instruction.operation {
             Operation::Add => {
-                arith!(|x, y| x + y, instruction, self);
+                arith!(|x, y| self.add(&x, &y), instruction, self);
             }
             Operation::Multiply => {
-                arith!(|x, y| x * y, instruction, self);
+                arith!(|x, y| self.multiply(&x, &y), instruction, self);
             }
             Operation::Input => {
-                if self.interrupted == Some(Interrupt::Input) || !self.input_buffer.is_empty() {
-                    self.interrupted = None; // bug?
-                    let input_result = self
-                        .input_buffer
-                        .pop_front()
-                        .expect("input buffer empty after interrupt");
-                    let storage_index =
-                        resolve_pointer(instruction.parameters[0], &self.relative_base);
-                    self.memory.insert(storage_index, input_result);
-                } else {
-                    self.interrupted = Some(Interrupt::Input);
-                    return;
+                let storage_index = self.resolve_write_pointer(&instruction.parameters[0])?;
+                match self.input_buffer.pop_front() {
+                    Some(input) => self.store(storage_index, input),
+                    None => return Ok(Some(Interrupt::Input)),
                 }
             }
             Operation::Output => {
-                if Some(Interrupt::Output) == self.interrupted {
-                    self.interrupted = None;
-                } else {
-                    let value = resolve_value_in_memory(
-                        instruction.parameters[0],
-                        &self.memory,
-                        &self.relative_base,
-                    );
-                    self.output_buffer.push_back(value);
-                    self.interrupted = Some(Interrupt::Output);
-                    return;
-                }
+                let value = self.resolve_value(&instruction.parameters[0])?;
+                self.output_buffer.push_back(value);
+                self.advance_instruction_pointer(&instruction);
+                return Ok(Some(Interrupt::Output));
             }
             Operation::JumpIfTrue => {
-                if resolve_value_in_memory(
-                    instruction.parameters[0],
-                    &self.memory,
-                    &self.relative_base,
-                ) != 0
-                {
-                    let jump_address = resolve_value_in_memory(
-                        instruction.parameters[1],
-                        &self.memory,
-                        &self.relative_base,
-                    );
-                    self.instruction_ptr = jump_address as usize;
-                    return;
+                if self.resolve_value(&instruction.parameters[0])? != M::Cell::default() {
+                    let jump_address = self.resolve_value(&instruction.parameters[1])?;
+                    self.instruction_ptr = self.checked_address(jump_address)?;
+                    return Ok(None);
                 }
             }
             Operation::JumpIfFalse => {
-                if resolve_value_in_memory(
-                    instruction.parameters[0],
-                    &self.memory,
-                    &self.relative_base,
-                ) == 0
-                {
-                    let jump_address = resolve_value_in_memory(
-                        instruction.parameters[1],
-                        &self.memory,
-                        &self.relative_base,
-                    );
-                    self.instruction_ptr = jump_address as usize;
-                    return;
+                if self.resolve_value(&instruction.parameters[0])? == M::Cell::default() {
+                    let jump_address = self.resolve_value(&instruction.parameters[1])?;
+                    self.instruction_ptr = self.checked_address(jump_address)?;
+                    return Ok(None);
                 }
             }
             Operation::LessThan => {
-                arith!(|x, y| if x < y { 1 } else { 0 }, instruction, self);
+                arith!(
+                    |x, y| Some(M::Cell::from(if x < y { 1 } else { 0 })),
+                    instruction,
+                    self
+                );
             }
             Operation::Equals => {
-                arith!(|x, y| if x == y { 1 } else { 0 }, instruction, self);
+                arith!(
+                    |x, y| Some(M::Cell::from(if x == y { 1 } else { 0 })),
+                    instruction,
+                    self
+                );
             }
             Operation::AdjustRelativeBase => {
-                let delta_base = resolve_value_in_memory(
-                    instruction.parameters[0],
-                    &self.memory,
-                    &self.relative_base,
-                );
-                self.relative_base += delta_base;
+                let delta_base = self.resolve_value(&instruction.parameters[0])?;
+                self.relative_base = self
+                    .add(&self.relative_base, &delta_base)
+                    .ok_or_else(|| self.overflow())?;
             }
-            Operation::Halt => {
-                self.interrupted = Some(Interrupt::Halt);
-                return;
+            Operation::Halt => return Ok(Some(Interrupt::Halt)),
+            Operation::Extension(extension) => {
+                let storage_index =
+                    self.resolve_write_pointer(&instruction.parameters[extension.inputs])?;
+                let inputs = instruction.parameters[..extension.inputs]
+                    .iter()
+                    .map(|parameter| self.resolve_value(parameter))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let result = self
+                    .instruction_set
+                    .evaluate(extension, &inputs)
+                    .ok_or_else(|| IntcodeError::InvalidOperands {
+                        instruction_ptr: self.instruction_ptr,
+                        opcode: self.current_opcode(),
+                    })?;
+                self.store(storage_index, result);
             }
         }
         self.advance_instruction_pointer(&instruction);
+        Ok(None)
+    }
+
+    fn check_limits(&self) -> Result<(), IntcodeError<M::Cell>> {
+        if self.step_limit.is_some_and(|limit| self.steps >= limit) {
+            return Err(IntcodeError::StepLimitExceeded {
+                instruction_ptr: self.instruction_ptr,
+                opcode: self.current_opcode(),
+                steps: self.steps,
+            });
+        }
+        if let Some(deadline) = self.deadline {
+            if self.steps.is_multiple_of(DEADLINE_CHECK_INTERVAL) && Instant::now() >= deadline {
+                return Err(IntcodeError::TimedOut {
+                    instruction_ptr: self.instruction_ptr,
+                    opcode: self.current_opcode(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn store(&mut self, address: usize, value: M::Cell) {
+        if self.history.is_some() {
+            self.undo_writes.push((address, self.memory.read(address)));
+        }
+        if self.tracer.is_some() || !self.watchpoints.is_empty() {
+            self.writes.push((address, value.clone()));
+        }
+        self.memory
+            .write(address, value)
+            .expect("write addresses are checked against the memory's max_len");
+        self.decode_cache.invalidate(address);
+    }
+
+    // `None` if it overflowed with overflow checks on
+    fn add(&self, x: &M::Cell, y: &M::Cell) -> Option<M::Cell> {
+        if self.overflow_checks {
+            x.checked_add(y)
+        } else {
+            Some(x.wrapping_add(y))
+        }
+    }
+
+    fn multiply(&self, x: &M::Cell, y: &M::Cell) -> Option<M::Cell> {
+        if self.overflow_checks {
+            x.checked_mul(y)
+        } else {
+            Some(x.wrapping_mul(y))
+        }
+    }
+
+    fn overflow(&self) -> IntcodeError<M::Cell> {
+        IntcodeError::Overflow {
+            instruction_ptr: self.instruction_ptr,
+            opcode: self.current_opcode(),
+        }
     }
 
-    fn advance_instruction_pointer(&mut self, instruction: &Instruction) {
+    fn resolve_operands(&self, instruction: &DecodedInstruction<M::Cell>) -> Vec<M::Cell> {
+        let write_parameter = instruction.operation.write_parameter();
+        instruction
+            .parameters()
+            .iter()
+            .enumerate()
+            .map(|(index, parameter)| match write_parameter {
+                Some(write) if write == index => self
+                    .resolve_pointer(parameter)
+                    .map(|address| M::Cell::from(address as i64)),
+                _ => self.resolve_value(parameter),
+            })
+            .collect::<Result<_, _>>()
+            // the instruction is about to fault anyway
+            .unwrap_or_default()
+    }
+
+    fn watching(&self, access: Access) -> bool {
+        self.watchpoints
+            .iter()
+            .any(|watchpoint| watchpoint.access == access)
+    }
+
+    /// The addresses and values of the memory operands the instruction is about to read.
+    fn operand_reads(&self, instruction: &DecodedInstruction<M::Cell>) -> Vec<(usize, M::Cell)> {
+        let write_parameter = instruction.operation.write_parameter();
+        // a jump only reads its target if it's taken
+        let is_zero = self
+            .resolve_value(&instruction.parameters[0])
+            .map(|condition| condition == M::Cell::default());
+        let reads_target = match instruction.operation {
+            Operation::JumpIfTrue => is_zero != Ok(true),
+            Operation::JumpIfFalse => is_zero == Ok(true),
+            _ => true,
+        };
+        instruction
+            .parameters()
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| Some(*index) != write_parameter && (*index == 0 || reads_target))
+            .filter_map(|(_, parameter)| match parameter {
+                Parameter::Value(_) => None,
+                pointer => self.resolve_pointer(pointer).ok(),
+            })
+            .map(|address| (address, self.memory.read(address)))
+            .collect()
+    }
+
+    fn check_watchpoints(&mut self, reads: &[(usize, M::Cell)], writes: &[(usize, M::Cell)]) {
+        let accesses = reads
+            .iter()
+            .map(|(address, value)| (Access::Read, *address, value))
+            .chain(
+                writes
+                    .iter()
+                    .map(|(address, value)| (Access::Write, *address, value)),
+            );
+        let mut hits = Vec::new();
+        for (access, address, value) in accesses {
+            let triggered = self.watchpoints.iter().any(|watchpoint| {
+                watchpoint.access == access
+                    && watchpoint.address == address
+                    && watchpoint
+                        .condition
+                        .as_ref()
+                        .is_none_or(|condition| condition.evaluate(self, value))
+            });
+            if triggered {
+                hits.push(WatchHit {
+                    address,
+                    access,
+                    value: value.clone(),
+                });
+            }
+        }
+        self.watch_hits = hits;
+    }
+
+    fn advance_instruction_pointer(&mut self, instruction: &DecodedInstruction<M::Cell>) {
         self.instruction_ptr += 1 + instruction.operation.parameter_count();
     }
-}
 
-fn resolve_value_in_memory(
-    parameter: Parameter,
-    memory: &InternalMemoryType,
-    relative_base: &IntcodeMemoryCellType,
-) -> IntcodeMemoryCellType {
-    match parameter {
-        Parameter::Value(value) => value,
-        pointer => memory
-            .get(&resolve_pointer(pointer, relative_base))
-            .copied()
-            .unwrap_or_default(),
+    fn current_opcode(&self) -> M::Cell {
+        self.memory.read(self.instruction_ptr)
     }
-}
 
-fn resolve_pointer(parameter: Parameter, relative_base: &IntcodeMemoryCellType) -> usize {
-    match parameter {
-        Parameter::Value(_) => panic!("attempting to access value as pointer"),
-        Parameter::Pointer(index) => index,
-        Parameter::Relative(offset) => (offset + relative_base) as usize,
+    fn checked_address(&self, address: M::Cell) -> Result<usize, IntcodeError<M::Cell>> {
+        if address < M::Cell::default() {
+            return Err(IntcodeError::NegativeAddress {
+                instruction_ptr: self.instruction_ptr,
+                opcode: self.current_opcode(),
+                address,
+            });
+        }
+        match address
+            .to_i64()
+            .and_then(|index| usize::try_from(index).ok())
+        {
+            Some(index) => Ok(index),
+            None => Err(IntcodeError::AddressTooLarge {
+                instruction_ptr: self.instruction_ptr,
+                opcode: self.current_opcode(),
+                address,
+            }),
+        }
+    }
+
+    fn resolve_value(
+        &self,
+        parameter: &Parameter<M::Cell>,
+    ) -> Result<M::Cell, IntcodeError<M::Cell>> {
+        match parameter {
+            Parameter::Value(value) => Ok(value.clone()),
+            pointer => {
+                let address = self.resolve_pointer(pointer)?;
+                Ok(self.memory.read(address))
+            }
+        }
+    }
+
+    fn resolve_write_pointer(
+        &self,
+        parameter: &Parameter<M::Cell>,
+    ) -> Result<usize, IntcodeError<M::Cell>> {
+        let address = self.resolve_pointer(parameter)?;
+        self.check_write_address(address)
+    }
+
+    fn check_write_address(&self, address: usize) -> Result<usize, IntcodeError<M::Cell>> {
+        let max_len = self.memory.max_len();
+        if address
+            >= self
+                .memory_limit
+                .map_or(max_len, |limit| limit.min(max_len))
+        {
+            return Err(IntcodeError::MemoryLimitExceeded {
+                instruction_ptr: self.instruction_ptr,
+                opcode: self.current_opcode(),
+                address,
+            });
+        }
+        Ok(address)
+    }
+
+    fn resolve_pointer(
+        &self,
+        parameter: &Parameter<M::Cell>,
+    ) -> Result<usize, IntcodeError<M::Cell>> {
+        match parameter {
+            Parameter::Value(_) => Err(IntcodeError::ImmediateWrite {
+                instruction_ptr: self.instruction_ptr,
+                opcode: self.current_opcode(),
+            }),
+            Parameter::Pointer(index) => Ok(*index),
+            Parameter::Relative(offset) => {
+                let address = self
+                    .add(offset, &self.relative_base)
+                    .ok_or_else(|| self.overflow())?;
+                self.checked_address(address)
+            }
+        }
+    }
+
+    fn fetch(&mut self) -> Result<DecodedInstruction<M::Cell>, IntcodeError<M::Cell>> {
+        if let Some(instruction) = self.decode_cache.get(self.instruction_ptr) {
+            return Ok(instruction);
+        }
+        let instruction = self.decode()?;
+        self.decode_cache
+            .insert(self.instruction_ptr, instruction.clone());
+        Ok(instruction)
+    }
+
+    pub fn current_instruction(&self) -> Result<Instruction<M::Cell>, IntcodeError<M::Cell>> {
+        self.decode().map(DecodedInstruction::into_instruction)
     }
-}
 
-impl Instruction {
-    fn read(memory: &InternalMemoryType, instruction_ptr: &usize) -> Instruction {
-        let opcode = memory[instruction_ptr];
+    fn decode(&self) -> Result<DecodedInstruction<M::Cell>, IntcodeError<M::Cell>> {
+        let opcode = self.current_opcode();
+        let code = opcode.to_i64().ok_or_else(|| IntcodeError::UnknownOpcode {
+            instruction_ptr: self.instruction_ptr,
+            opcode: opcode.clone(),
+        })?;
         let OpCode {
             operation,
             parameter_modes,
-        } = OpCode::from(opcode);
-        let mut parameters: Vec<Parameter> = Vec::new();
-        for i in 0..operation.parameter_count() {
-            let mode = parameter_modes[i as usize];
-            let address = *instruction_ptr + i as usize + 1;
-            let value = memory[&address];
+        } = OpCode::decode(code, &*self.instruction_set)
+            .map_err(|error| IntcodeError::decode(self.instruction_ptr, opcode.clone(), error))?;
+        let mut parameters: [Parameter<M::Cell>; 3] =
+            std::array::from_fn(|_| Parameter::Value(M::Cell::default()));
+        for (i, mode) in parameter_modes.into_iter().enumerate() {
+            let address = self.instruction_ptr + i + 1;
+            if address >= self.memory.len() {
+                return Err(IntcodeError::TruncatedInstruction {
+                    instruction_ptr: self.instruction_ptr,
+                    opcode: opcode.clone(),
+                });
+            }
+            let value = self.memory.read(address);
             let parameter = match mode {
-                ParameterMode::Pointer => Parameter::Pointer(value as usize),
+                ParameterMode::Pointer => Parameter::Pointer(self.checked_address(value)?),
                 ParameterMode::Value => Parameter::Value(value),
                 ParameterMode::Relative => Parameter::Relative(value),
             };
-            parameters.push(parameter);
-        }
-        Instruction {
-            operation: operation,
-            parameters: parameters,
+            parameters[i] = parameter;
         }
+        Ok(DecodedInstruction {
+            operation,
+            parameters,
+        })
     }
 }
 
@@ -244,3 +829,475 @@ pub enum Interrupt {
     Output,
     Halt,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_opcode_faults() {
+        let mut computer = IntCodeComputer::new(vec![1101, 1, 1, 5, 42, 0]);
+        assert_eq!(
+            computer.execute(),
+            Err(IntcodeError::UnknownOpcode {
+                instruction_ptr: 4,
+                opcode: 42
+            })
+        );
+    }
+
+    #[test]
+    fn test_unknown_parameter_mode_faults() {
+        let mut computer = IntCodeComputer::new(vec![301, 0, 0, 0, 99]);
+        assert_eq!(
+            computer.execute(),
+            Err(IntcodeError::UnknownParameterMode {
+                instruction_ptr: 0,
+                opcode: 301,
+                mode: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_immediate_write_faults() {
+        let mut computer = IntCodeComputer::new(vec![11101, 1, 1, 0, 99]);
+        assert_eq!(
+            computer.execute(),
+            Err(IntcodeError::ImmediateWrite {
+                instruction_ptr: 0,
+                opcode: 11101
+            })
+        );
+    }
+
+    #[test]
+    fn test_negative_address_faults() {
+        let mut computer = IntCodeComputer::new(vec![1105, 1, -3, 99]);
+        assert_eq!(
+            computer.execute(),
+            Err(IntcodeError::NegativeAddress {
+                instruction_ptr: 0,
+                opcode: 1105,
+                address: -3
+            })
+        );
+    }
+
+    #[test]
+    fn test_truncated_instruction_faults() {
+        let mut computer = IntCodeComputer::new(vec![1, 0, 0]);
+        assert_eq!(
+            computer.execute(),
+            Err(IntcodeError::TruncatedInstruction {
+                instruction_ptr: 0,
+                opcode: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_input_interrupt_resumes() {
+        let mut computer = IntCodeComputer::new(vec![3, 0, 99]);
+        assert_eq!(computer.execute(), Ok(Interrupt::Input));
+        assert_eq!(computer.execute(), Ok(Interrupt::Input));
+        computer.provide_input(7);
+        assert_eq!(computer.execute(), Ok(Interrupt::Halt));
+        assert_eq!(computer.terminate(), vec![7, 0, 99]);
+    }
+
+    #[test]
+    fn test_step_through_output() {
+        let mut computer = IntCodeComputer::new(vec![104, 5, 99]);
+        assert_eq!(computer.step(), Ok(Some(Interrupt::Output)));
+        assert_eq!(computer.instruction_ptr(), 2);
+        assert_eq!(computer.take_output(), 5);
+        assert_eq!(computer.step(), Ok(Some(Interrupt::Halt)));
+        assert_eq!(computer.step(), Ok(Some(Interrupt::Halt)));
+        assert_eq!(computer.instruction_ptr(), 2);
+    }
+
+    #[test]
+    fn test_terminate_fills_gaps() {
+        let mut computer = IntCodeComputer::new(vec![1101, 2, 3, 7, 99]);
+        computer.execute().unwrap();
+        assert_eq!(computer.terminate(), vec![1101, 2, 3, 7, 99, 0, 0, 5]);
+    }
+
+    #[test]
+    fn test_sparse_memory_computer() {
+        let program: IntcodeMemoryType = vec![21101, 4, 5, 0, 99];
+        let mut computer = IntCodeComputer::with_memory(memory::SparseMemory::from(program));
+        computer.set_memory_limit(None);
+        computer.relative_base = 1 << 40;
+        computer.execute().unwrap();
+        assert_eq!(computer.read_memory(1 << 40), 9);
+
+        // too big for a full memory image
+        let snapshot = computer.snapshot();
+        assert!(snapshot
+            .to_string()
+            .ends_with("cells 0:21101,1:4,2:5,3:0,4:99,1099511627776:9\n"));
+        let loaded: Snapshot<memory::SparseMemory> = snapshot.to_string().parse().unwrap();
+        assert_eq!(loaded, snapshot);
+        assert_eq!(
+            snapshot.to_string().parse::<Snapshot>(),
+            Err("line 7: address 1099511627776 is out of range".to_string())
+        );
+    }
+
+    #[test]
+    fn test_self_modifying_code() {
+        // bumps the operand of the WRITE at 4 before every pass through the loop
+        let program = vec![
+            1001, 5, 1, 5, 104, 0, 1001, 20, 1, 20, 1007, 20, 3, 21, 1005, 21, 0, 99, 0, 0, 0, 0,
+        ];
+        let mut computer = IntCodeComputer::new(program);
+        let mut outputs = Vec::new();
+        while computer.execute() == Ok(Interrupt::Output) {
+            outputs.push(computer.take_output());
+        }
+        assert_eq!(outputs, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_clone_forks_the_machine() {
+        // reads a value and outputs it doubled
+        let mut computer = IntCodeComputer::new(vec![3, 9, 1002, 9, 2, 9, 4, 9, 99, 0]);
+        assert_eq!(computer.execute(), Ok(Interrupt::Input));
+        let mut fork = computer.clone();
+        computer.provide_input(3);
+        fork.provide_input(5);
+        assert_eq!(computer.execute(), Ok(Interrupt::Output));
+        assert_eq!(fork.execute(), Ok(Interrupt::Output));
+        assert_eq!((computer.take_output(), fork.take_output()), (6, 10));
+    }
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        let mut computer = IntCodeComputer::new(vec![3, 9, 1002, 9, 2, 9, 4, 9, 99, 0]);
+        computer.provide_input(3);
+        computer.step().unwrap();
+        let snapshot = computer.snapshot();
+        assert_eq!(computer.execute(), Ok(Interrupt::Output));
+        assert_eq!(computer.take_output(), 6);
+
+        computer.restore(&snapshot);
+        assert_eq!(computer.instruction_ptr(), 2);
+        assert_eq!(computer.execute(), Ok(Interrupt::Output));
+        assert_eq!(computer.take_output(), 6);
+
+        let path = std::env::temp_dir().join(format!("intcode_snapshot_{}", std::process::id()));
+        snapshot.save(&path).unwrap();
+        let loaded: Snapshot = Snapshot::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, snapshot);
+        assert_eq!(
+            snapshot.to_string(),
+            "intcode snapshot\nip 2\nrelative_base 0\ninterrupted none\ninput \noutput \nmemory 3,9,1002,9,2,9,4,9,99,3\n"
+        );
+    }
+
+    #[test]
+    fn test_step_back() {
+        // reads a value, doubles it into memory[13] and outputs it, then adds 5 to it
+        let program = vec![3, 13, 1002, 13, 2, 13, 4, 13, 1001, 13, 5, 13, 99, 0];
+        let mut computer = IntCodeComputer::new(program.clone());
+        computer.record_history(100);
+        computer.provide_input(4);
+        assert_eq!(computer.execute(), Ok(Interrupt::Output));
+        assert_eq!(computer.execute(), Ok(Interrupt::Halt));
+        assert_eq!(computer.read_memory(13), 13);
+
+        assert_eq!(computer.step_back(1), 1);
+        assert_eq!(computer.instruction_ptr(), 12);
+        assert_eq!(computer.step_back(1), 1);
+        assert_eq!(computer.instruction_ptr(), 8);
+        assert_eq!(computer.read_memory(13), 8);
+        assert_eq!(computer.step_back(2), 2);
+        assert_eq!(computer.instruction_ptr(), 2);
+        assert!(computer.pending_output().is_empty());
+        assert_eq!(computer.step_back(5), 1);
+        assert_eq!(computer.steps(), 0);
+        assert_eq!(computer.pending_input(), &VecDeque::from(vec![4]));
+        assert_eq!(computer.terminate(), program);
+    }
+
+    #[test]
+    fn test_step_back_over_taken_output() {
+        let mut computer = IntCodeComputer::new(vec![104, 1, 104, 2, 104, 3, 99]);
+        computer.record_history(100);
+        assert_eq!(computer.execute(), Ok(Interrupt::Output));
+        assert_eq!(computer.execute(), Ok(Interrupt::Output));
+        assert_eq!(computer.take_output(), 1);
+        assert_eq!(computer.step_back(1), 1);
+        assert!(computer.pending_output().is_empty());
+
+        assert_eq!(computer.execute(), Ok(Interrupt::Output));
+        assert_eq!(computer.execute(), Ok(Interrupt::Output));
+        assert_eq!(computer.take_output(), 2);
+        assert_eq!(computer.step_back(1), 1);
+        assert!(computer.pending_output().is_empty());
+        assert_eq!(computer.step_back(1), 1);
+        assert_eq!(computer.instruction_ptr(), 2);
+        assert!(computer.pending_output().is_empty());
+    }
+
+    #[test]
+    fn test_step_back_to_write() {
+        // writes memory[9], then memory[10]
+        let mut computer = IntCodeComputer::new(vec![1101, 1, 1, 9, 1101, 2, 2, 10, 99, 0, 0]);
+        computer.record_history(100);
+        computer.execute().unwrap();
+        assert_eq!(computer.step_back_to_write(11), None);
+        assert_eq!(computer.instruction_ptr(), 8);
+        assert_eq!(computer.step_back_to_write(9), Some(3));
+        assert_eq!(computer.instruction_ptr(), 0);
+        assert_eq!(computer.read_memory(9), 0);
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let mut computer = IntCodeComputer::new(vec![1101, 1, 1, 9, 1101, 2, 2, 10, 99, 0, 0]);
+        computer.record_history(1);
+        computer.execute().unwrap();
+        assert_eq!(computer.history_len(), 1);
+        assert_eq!(computer.step_back(2), 1);
+        assert_eq!(computer.instruction_ptr(), 8);
+        assert_eq!(computer.read_memory(10), 4);
+    }
+
+    #[test]
+    fn test_conditional_breakpoint() {
+        // counts memory[10] down from 3
+        let mut computer = IntCodeComputer::new(vec![1001, 10, -1, 10, 1005, 10, 0, 99, 0, 0, 3]);
+        computer.set_breakpoint(4, Some("[10] == 1".parse().unwrap()));
+        assert_eq!(computer.execute_until_break(), Ok(Stop::Breakpoint(4)));
+        assert_eq!(computer.read_memory(10), 1);
+        assert_eq!(
+            computer.execute_until_break(),
+            Ok(Stop::Interrupt(Interrupt::Halt))
+        );
+    }
+
+    #[test]
+    fn test_watchpoints() {
+        // counts memory[10] down from 3
+        let mut computer = IntCodeComputer::new(vec![1001, 10, -1, 10, 1005, 10, 0, 99, 0, 0, 3]);
+        computer.add_watchpoint(Watchpoint {
+            address: 10,
+            access: Access::Write,
+            condition: Some("value < 2".parse().unwrap()),
+        });
+        let hit = WatchHit {
+            address: 10,
+            access: Access::Write,
+            value: 1,
+        };
+        assert_eq!(computer.execute_until_break(), Ok(Stop::Watchpoint(hit)));
+        assert_eq!(computer.instruction_ptr(), 4);
+
+        assert!(computer.remove_watchpoints(10));
+        computer.add_watchpoint(Watchpoint {
+            address: 10,
+            access: Access::Read,
+            condition: None,
+        });
+        let hit = WatchHit {
+            access: Access::Read,
+            ..hit
+        };
+        assert_eq!(computer.execute_until_break(), Ok(Stop::Watchpoint(hit)));
+        assert_eq!(computer.instruction_ptr(), 0);
+    }
+
+    #[test]
+    fn test_step_limit() {
+        let mut computer = IntCodeComputer::new(vec![1105, 1, 0]);
+        computer.set_step_limit(Some(10));
+        assert_eq!(
+            computer.execute(),
+            Err(IntcodeError::StepLimitExceeded {
+                instruction_ptr: 0,
+                opcode: 1105,
+                steps: 10
+            })
+        );
+    }
+
+    #[test]
+    fn test_timeout() {
+        let mut computer = IntCodeComputer::new(vec![1105, 1, 0]);
+        computer.set_timeout(Some(Duration::from_millis(10)));
+        assert_eq!(
+            computer.execute(),
+            Err(IntcodeError::TimedOut {
+                instruction_ptr: 0,
+                opcode: 1105
+            })
+        );
+        computer.set_timeout(Some(Duration::MAX));
+        assert_eq!(computer.deadline, None);
+    }
+
+    #[test]
+    fn test_memory_limit() {
+        // reads into memory[100]
+        let mut computer = IntCodeComputer::new(vec![3, 100, 99]);
+        computer.set_memory_limit(Some(100));
+        computer.provide_input(1);
+        assert_eq!(
+            computer.execute(),
+            Err(IntcodeError::MemoryLimitExceeded {
+                instruction_ptr: 0,
+                opcode: 3,
+                address: 100
+            })
+        );
+        assert_eq!(computer.pending_input().len(), 1);
+    }
+
+    #[test]
+    fn test_huge_writes_fault_by_default() {
+        let program = vec![1101, 1, 1, 1_000_000_000_000_000, 99];
+        let mut computer = IntCodeComputer::new(program.clone());
+        assert_eq!(
+            computer.execute(),
+            Err(IntcodeError::MemoryLimitExceeded {
+                instruction_ptr: 0,
+                opcode: 1101,
+                address: 1_000_000_000_000_000
+            })
+        );
+        assert!(matches!(
+            computer.write_memory(usize::MAX, 1),
+            Err(IntcodeError::MemoryLimitExceeded { .. })
+        ));
+        let mut computer = IntCodeComputer::new(program);
+        computer.set_memory_limit(None);
+        assert!(matches!(
+            computer.execute(),
+            Err(IntcodeError::MemoryLimitExceeded { .. })
+        ));
+        assert_eq!(computer.terminate().len(), 5);
+    }
+
+    #[test]
+    fn test_overflow_checks() {
+        // doubles i64::MAX, then moves the relative base past it
+        let program = vec![1102, i64::MAX, 2, 9, 4, 9, 109, i64::MAX, 109, 1, 99];
+        let mut computer = IntCodeComputer::new(program.clone());
+        assert_eq!(computer.execute(), Ok(Interrupt::Output));
+        assert_eq!(computer.take_output(), -2);
+
+        let mut computer = IntCodeComputer::new(program);
+        computer.set_overflow_checks(true);
+        assert_eq!(
+            computer.execute(),
+            Err(IntcodeError::Overflow {
+                instruction_ptr: 0,
+                opcode: 1102
+            })
+        );
+        computer.write_memory(2, 1).unwrap();
+        assert_eq!(computer.execute(), Ok(Interrupt::Output));
+        computer.take_output();
+        assert_eq!(
+            computer.execute(),
+            Err(IntcodeError::Overflow {
+                instruction_ptr: 8,
+                opcode: 109
+            })
+        );
+    }
+
+    #[test]
+    fn test_wider_cells() {
+        fn square<C: Cell>(value: C) -> Vec<C> {
+            let mut program: Vec<C> = vec![1102, 0, 0, 7, 4, 7, 99, 0]
+                .into_iter()
+                .map(C::from)
+                .collect();
+            program[1] = value.clone();
+            program[2] = value;
+            program
+        }
+        let mut computer =
+            IntCodeComputer::with_memory(DenseMemory::from(square(i64::MAX as i128)));
+        computer.set_overflow_checks(true);
+        assert_eq!(computer.execute(), Ok(Interrupt::Output));
+        assert_eq!(computer.take_output(), (i64::MAX as i128).pow(2));
+
+        let big = |value: &str| value.parse::<bigint::BigInt>().unwrap();
+        let program = square(big(&i128::MAX.to_string()));
+        let mut computer = IntCodeComputer::with_memory(DenseMemory::from(program));
+        computer.set_overflow_checks(true);
+        assert_eq!(computer.execute(), Ok(Interrupt::Output));
+        assert_eq!(
+            computer.take_output(),
+            big("28948022309329048855892746252171976962977213799489202546401021394546514198529")
+        );
+    }
+
+    #[test]
+    fn test_wide_addresses_fault() {
+        let huge = "100000000000000000000".parse::<bigint::BigInt>().unwrap();
+        let mut computer = IntCodeComputer::with_memory(DenseMemory::from(vec![
+            bigint::BigInt::from(4),
+            huge.clone(),
+            huge.clone(),
+        ]));
+        assert_eq!(
+            computer.execute(),
+            Err(IntcodeError::AddressTooLarge {
+                instruction_ptr: 0,
+                opcode: bigint::BigInt::from(4),
+                address: huge.clone()
+            })
+        );
+        computer.write_memory(0, huge.clone()).unwrap();
+        assert_eq!(
+            computer.execute(),
+            Err(IntcodeError::UnknownOpcode {
+                instruction_ptr: 0,
+                opcode: huge
+            })
+        );
+    }
+
+    #[test]
+    fn test_instruction_set_extensions() {
+        let mut isa = isa::ExtendedIsa::new();
+        isa.register(10, "DIV", 2, |x: &[i64]| x[0].checked_div(x[1]));
+        isa.register(11, "MOD", 2, |x: &[i64]| x[0].checked_rem(x[1]));
+        isa.register(12, "AND", 2, |x: &[i64]| Some(x[0] & x[1]));
+        isa.register(13, "OR", 2, |x: &[i64]| Some(x[0] | x[1]));
+        // (input / 4, input % 4, 12 & 10, 12 | 3)
+        let program = vec![
+            3, 21, 1010, 21, 4, 22, 1011, 21, 4, 23, 1112, 12, 10, 24, 1113, 12, 3, 25, 99, 0, 0,
+            0, 0, 0, 0, 0,
+        ];
+        let mut computer = IntCodeComputer::new(program.clone());
+        computer.provide_input(-23);
+        assert!(matches!(
+            computer.execute(),
+            Err(IntcodeError::UnknownOpcode { opcode: 1010, .. })
+        ));
+
+        let mut computer = IntCodeComputer::new(program);
+        computer.set_instruction_set(isa.clone());
+        computer.provide_input(-23);
+        assert_eq!(computer.execute(), Ok(Interrupt::Halt));
+        assert_eq!(computer.terminate()[22..], [-5, -3, 8, 15]);
+
+        let mut computer = IntCodeComputer::new(vec![1110, 1, 0, 5, 99, 0]);
+        computer.set_instruction_set(isa);
+        assert_eq!(
+            computer.execute(),
+            Err(IntcodeError::InvalidOperands {
+                instruction_ptr: 0,
+                opcode: 1110
+            })
+        );
+    }
+}
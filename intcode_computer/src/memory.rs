@@ -0,0 +1,209 @@
+use super::cell::Cell;
+use super::IntcodeMemoryCellType;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+/// Addressable storage for a computer. Unwritten addresses read as 0.
+pub trait Memory {
+    type Cell: Cell;
+
+    fn read(&self, address: usize) -> Self::Cell;
+    /// Fails without changing anything if `address` is at or past `max_len`.
+    fn write(&mut self, address: usize, value: Self::Cell) -> Result<(), OutOfRange>;
+    /// One past the highest address loaded or written.
+    fn len(&self) -> usize;
+    /// Every cell that's stored, by address.
+    fn cells(&self) -> Vec<(usize, Self::Cell)>;
+    /// The most cells this memory can hold. The computer faults instead of writing past it,
+    /// whatever its own memory limit.
+    fn max_len(&self) -> usize;
+
+    /// A contiguous image of memory, with any gaps filled with 0, or `None` if that would take
+    /// more than `MAX_DENSE_LEN` cells.
+    fn to_vec(&self) -> Option<Vec<Self::Cell>> {
+        if self.len() > MAX_DENSE_LEN {
+            return None;
+        }
+        let mut result = vec![Self::Cell::default(); self.len()];
+        for (address, value) in self.cells() {
+            result[address] = value;
+        }
+        Some(result)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A write to an address past the most a memory can hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfRange {
+    pub address: usize,
+}
+
+impl fmt::Display for OutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "address {} is out of range", self.address)
+    }
+}
+
+impl Error for OutOfRange {}
+
+/// Every address below this can be stored, whichever the program writes to first.
+pub const MAX_DENSE_LEN: usize = 1 << 26;
+
+/// The default memory, a `Vec` that grows to fit whatever address is written, up to
+/// `MAX_DENSE_LEN` cells.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DenseMemory<C = IntcodeMemoryCellType> {
+    cells: Vec<C>,
+}
+
+impl<C> From<Vec<C>> for DenseMemory<C> {
+    fn from(cells: Vec<C>) -> Self {
+        DenseMemory { cells }
+    }
+}
+
+impl<C> DenseMemory<C> {
+    pub fn into_vec(self) -> Vec<C> {
+        self.cells
+    }
+}
+
+impl<C: Cell> Memory for DenseMemory<C> {
+    type Cell = C;
+
+    fn read(&self, address: usize) -> C {
+        self.cells.get(address).cloned().unwrap_or_default()
+    }
+
+    fn write(&mut self, address: usize, value: C) -> Result<(), OutOfRange> {
+        if address >= self.cells.len() {
+            if address >= MAX_DENSE_LEN {
+                return Err(OutOfRange { address });
+            }
+            self.cells.resize(address + 1, C::default());
+        }
+        self.cells[address] = value;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    fn cells(&self) -> Vec<(usize, C)> {
+        self.cells.iter().cloned().enumerate().collect()
+    }
+
+    fn max_len(&self) -> usize {
+        // loading can make it longer than it could grow to
+        self.cells.len().max(MAX_DENSE_LEN)
+    }
+
+    fn to_vec(&self) -> Option<Vec<C>> {
+        Some(self.cells.clone())
+    }
+}
+
+/// Only stores the addresses that were written, for programs that touch huge addresses.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SparseMemory<C = IntcodeMemoryCellType> {
+    cells: HashMap<usize, C>,
+    len: usize,
+}
+
+impl<C> From<Vec<C>> for SparseMemory<C> {
+    fn from(cells: Vec<C>) -> Self {
+        SparseMemory {
+            len: cells.len(),
+            cells: cells.into_iter().enumerate().collect(),
+        }
+    }
+}
+
+impl<C: Cell> Memory for SparseMemory<C> {
+    type Cell = C;
+
+    fn read(&self, address: usize) -> C {
+        self.cells.get(&address).cloned().unwrap_or_default()
+    }
+
+    fn write(&mut self, address: usize, value: C) -> Result<(), OutOfRange> {
+        if address >= self.max_len() {
+            return Err(OutOfRange { address });
+        }
+        self.cells.insert(address, value);
+        self.len = self.len.max(address + 1);
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn cells(&self) -> Vec<(usize, C)> {
+        let mut cells: Vec<_> = self
+            .cells
+            .iter()
+            .map(|(address, value)| (*address, value.clone()))
+            .collect();
+        cells.sort_by_key(|(address, _)| *address);
+        cells
+    }
+
+    fn max_len(&self) -> usize {
+        // so `len` always fits
+        usize::MAX
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IntcodeMemoryType;
+
+    fn exercise<M: Memory<Cell = IntcodeMemoryCellType> + From<IntcodeMemoryType>>() {
+        let mut memory = M::from(vec![1, 2]);
+        assert_eq!(memory.len(), 2);
+        assert_eq!(memory.write(5, 7), Ok(()));
+        assert_eq!(memory.read(5), 7);
+        assert_eq!(memory.read(3), 0);
+        assert_eq!(memory.read(100), 0);
+        assert_eq!(memory.len(), 6);
+        assert_eq!(memory.to_vec(), Some(vec![1, 2, 0, 0, 0, 7]));
+        assert_eq!(
+            memory.write(usize::MAX, 1),
+            Err(OutOfRange {
+                address: usize::MAX
+            })
+        );
+        assert_eq!(memory.len(), 6);
+    }
+
+    #[test]
+    fn test_dense_memory() {
+        exercise::<DenseMemory>();
+        let mut memory: DenseMemory = DenseMemory::default();
+        assert_eq!(
+            memory.write(MAX_DENSE_LEN, 1),
+            Err(OutOfRange {
+                address: MAX_DENSE_LEN
+            })
+        );
+        assert!(memory.is_empty());
+    }
+
+    #[test]
+    fn test_sparse_memory() {
+        exercise::<SparseMemory>();
+        let mut memory: SparseMemory = SparseMemory::default();
+        assert_eq!(memory.write(1 << 40, 3), Ok(()));
+        assert_eq!(memory.read(1 << 40), 3);
+        assert_eq!(memory.cells(), vec![(1 << 40, 3)]);
+        assert_eq!(memory.to_vec(), None);
+    }
+}
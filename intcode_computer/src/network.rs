@@ -0,0 +1,324 @@
+use super::{Computer, IntCodeComputer, IntcodeError, IntcodeMemoryCellType, Interrupt};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet {
+    pub destination: IntcodeMemoryCellType,
+    pub payload: Vec<IntcodeMemoryCellType>,
+}
+
+/// What a `Monitor` wants the network to do next.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Continue,
+    Send(Packet),
+    Stop,
+}
+
+/// Sits on every address no machine has, like a NAT, and gets told when the network goes idle.
+pub trait Monitor {
+    fn packet(&mut self, packet: Packet) -> Action;
+    fn idle(&mut self) -> Action;
+}
+
+impl<T: Monitor> Monitor for Arc<Mutex<T>> {
+    fn packet(&mut self, packet: Packet) -> Action {
+        self.lock().unwrap().packet(packet)
+    }
+
+    fn idle(&mut self) -> Action {
+        self.lock().unwrap().idle()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkError {
+    Fault {
+        address: usize,
+        error: IntcodeError,
+    },
+    /// A packet was sent to an address with no machine and there is no monitor to take it.
+    Unroutable {
+        destination: IntcodeMemoryCellType,
+    },
+    /// Every machine is idle and there is no monitor to wake them up.
+    Idle,
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fault { address, error } => write!(f, "machine {}: {}", address, error),
+            Self::Unroutable { destination } => {
+                write!(f, "no machine at address {}", destination)
+            }
+            Self::Idle => write!(f, "every machine is idle"),
+        }
+    }
+}
+
+impl Error for NetworkError {}
+
+struct Node {
+    computer: IntCodeComputer,
+    queue: VecDeque<IntcodeMemoryCellType>,
+    // output of a packet still being sent
+    outgoing: Vec<IntcodeMemoryCellType>,
+    // input requests in a row that found the queue empty
+    idle_polls: usize,
+    halted: bool,
+}
+
+/// Machines addressed by their position, exchanging packets of `[destination, payload...]`.
+///
+/// Machines take turns, each running until it asks for input its queue can't satisfy. That
+/// request is answered with the idle value if there is one, otherwise the machine waits for a
+/// packet. The network is idle once every queue is empty and every machine has come up empty
+/// `idle_threshold` times in a row.
+pub struct Network {
+    nodes: Vec<Node>,
+    packet_size: usize,
+    idle_value: Option<IntcodeMemoryCellType>,
+    idle_threshold: usize,
+    monitor: Option<Box<dyn Monitor + Send>>,
+}
+
+impl Network {
+    pub fn new(machines: Vec<IntCodeComputer>) -> Network {
+        Network {
+            nodes: machines
+                .into_iter()
+                .map(|computer| Node {
+                    computer,
+                    queue: VecDeque::new(),
+                    outgoing: Vec::new(),
+                    idle_polls: 0,
+                    halted: false,
+                })
+                .collect(),
+            packet_size: 3,
+            idle_value: None,
+            idle_threshold: 2,
+            monitor: None,
+        }
+    }
+
+    /// Runs `count` copies of `program`, each given its address as its first input.
+    pub fn boot(program: &[IntcodeMemoryCellType], count: usize) -> Network {
+        let mut network = Network::new(
+            (0..count)
+                .map(|_| IntCodeComputer::new(program.to_vec()))
+                .collect(),
+        );
+        for address in 0..count {
+            network.nodes[address]
+                .queue
+                .push_back(address as IntcodeMemoryCellType);
+        }
+        network
+    }
+
+    /// How many values make up a packet, counting the destination. Defaults to 3.
+    pub fn set_packet_size(&mut self, size: usize) {
+        assert!(size > 0, "packets need at least a destination");
+        self.packet_size = size;
+    }
+
+    pub fn set_idle_value(&mut self, value: Option<IntcodeMemoryCellType>) {
+        self.idle_value = value;
+    }
+
+    pub fn set_idle_threshold(&mut self, polls: usize) {
+        self.idle_threshold = polls;
+    }
+
+    pub fn set_monitor(&mut self, monitor: Box<dyn Monitor + Send>) {
+        self.monitor = Some(monitor);
+    }
+
+    pub fn machine(&self, address: usize) -> &IntCodeComputer {
+        &self.nodes[address].computer
+    }
+
+    pub fn send(&mut self, packet: Packet) -> Result<Action, NetworkError> {
+        let address = packet.destination;
+        if address >= 0 && (address as usize) < self.nodes.len() {
+            let node = &mut self.nodes[address as usize];
+            node.queue.extend(packet.payload);
+            node.idle_polls = 0;
+            return Ok(Action::Continue);
+        }
+        match self.monitor.as_mut() {
+            Some(monitor) => Ok(monitor.packet(packet)),
+            None => Err(NetworkError::Unroutable {
+                destination: address,
+            }),
+        }
+    }
+
+    /// Runs until the monitor stops the network or every machine halts.
+    pub fn run(&mut self) -> Result<(), NetworkError> {
+        loop {
+            for address in 0..self.nodes.len() {
+                if self.run_node(address)? == Action::Stop {
+                    return Ok(());
+                }
+            }
+            if self.nodes.iter().all(|node| node.halted) {
+                return Ok(());
+            }
+            if self.is_idle() {
+                let action = match self.monitor.as_mut() {
+                    Some(monitor) => monitor.idle(),
+                    None => return Err(NetworkError::Idle),
+                };
+                if self.perform(action)? == Action::Stop {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        self.nodes.iter().all(|node| {
+            node.halted
+                || (node.queue.is_empty()
+                    && node.outgoing.is_empty()
+                    && node.idle_polls >= self.idle_threshold)
+        })
+    }
+
+    fn perform(&mut self, action: Action) -> Result<Action, NetworkError> {
+        match action {
+            Action::Send(packet) => {
+                let action = self.send(packet)?;
+                self.perform(action)
+            }
+            action => Ok(action),
+        }
+    }
+
+    /// Runs one machine until it has to wait for input, returning `Stop` if the monitor said so.
+    fn run_node(&mut self, address: usize) -> Result<Action, NetworkError> {
+        let packet_size = self.packet_size;
+        loop {
+            let node = &mut self.nodes[address];
+            if node.halted {
+                return Ok(Action::Continue);
+            }
+            let interrupt = node
+                .computer
+                .execute()
+                .map_err(|error| NetworkError::Fault { address, error })?;
+            match interrupt {
+                Interrupt::Input => match node.queue.pop_front() {
+                    Some(value) => node.computer.provide_input(value),
+                    None => {
+                        node.idle_polls += 1;
+                        if let Some(value) = self.idle_value {
+                            node.computer.provide_input(value);
+                        }
+                        return Ok(Action::Continue);
+                    }
+                },
+                Interrupt::Output => {
+                    node.idle_polls = 0;
+                    node.outgoing.push(node.computer.take_output());
+                    if node.outgoing.len() == packet_size {
+                        let mut values = node.outgoing.drain(..);
+                        let packet = Packet {
+                            destination: values.next().unwrap(),
+                            payload: values.collect(),
+                        };
+                        let action = self.send(packet)?;
+                        if self.perform(action)? == Action::Stop {
+                            return Ok(Action::Stop);
+                        }
+                    }
+                }
+                Interrupt::Halt => {
+                    node.halted = true;
+                    return Ok(Action::Continue);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reads its address, and machine 0 sends [7, 8] to machine 1. Then every machine forwards
+    // each packet (x, y) it receives to 255 as (x, y + address).
+    const PROGRAM: [IntcodeMemoryCellType; 35] = [
+        3, 50, 1005, 50, 11, 104, 1, 104, 7, 104, 8, 3, 51, 1008, 51, -1, 52, 1005, 52, 11, 3, 53,
+        1, 53, 50, 53, 104, 255, 4, 51, 4, 53, 1105, 1, 11,
+    ];
+
+    /// Like the day 23 NAT: holds the last packet sent to it and sends it on to machine 0 when
+    /// the network is idle, until it would send the same packet twice in a row.
+    #[derive(Default)]
+    struct Nat {
+        last: Option<Packet>,
+        received: usize,
+        sent: Vec<Packet>,
+    }
+
+    impl Monitor for Nat {
+        fn packet(&mut self, packet: Packet) -> Action {
+            self.last = Some(packet);
+            self.received += 1;
+            Action::Continue
+        }
+
+        fn idle(&mut self) -> Action {
+            let packet = Packet {
+                destination: 0,
+                ..self.last.clone().unwrap()
+            };
+            if self.sent.last() == Some(&packet) {
+                return Action::Stop;
+            }
+            self.sent.push(packet.clone());
+            Action::Send(packet)
+        }
+    }
+
+    fn boot() -> Network {
+        let mut network = Network::boot(&PROGRAM, 2);
+        network.set_idle_value(Some(-1));
+        network
+    }
+
+    #[test]
+    fn test_nat() {
+        let nat = Arc::new(Mutex::new(Nat::default()));
+        let mut network = boot();
+        network.set_monitor(Box::new(nat.clone()));
+        assert_eq!(network.run(), Ok(()));
+        let packet = Packet {
+            destination: 0,
+            payload: vec![7, 9],
+        };
+        let nat = nat.lock().unwrap();
+        assert_eq!(nat.sent, vec![packet]);
+        assert_eq!(nat.received, 2);
+    }
+
+    #[test]
+    fn test_without_monitor() {
+        let mut network = boot();
+        assert_eq!(
+            network.run(),
+            Err(NetworkError::Unroutable { destination: 255 })
+        );
+        // polls forever
+        let mut network = Network::boot(&[3, 5, 1105, 1, 0, 0], 2);
+        network.set_idle_value(Some(-1));
+        assert_eq!(network.run(), Err(NetworkError::Idle));
+    }
+}
@@ -1,7 +1,16 @@
+use super::isa::{InstructionSet, StandardIsa, MAX_EXTENSION_INPUTS};
 use super::parameter::ParameterMode;
 use super::IntcodeMemoryCellType;
+use std::convert::TryFrom;
+use std::fmt;
 
 #[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum DecodeError {
+    UnknownOpcode(IntcodeMemoryCellType),
+    UnknownParameterMode(IntcodeMemoryCellType),
+}
+
+#[derive(PartialEq, Eq, Hash, Debug, Copy, Clone)]
 pub enum Operation {
     Add,
     Multiply,
@@ -13,22 +22,40 @@ pub enum Operation {
     Equals,
     AdjustRelativeBase,
     Halt,
+    Extension(Extension),
+}
+
+/// An operation an `InstructionSet` adds to the standard ones. It reads `inputs` values and
+/// writes its result to one more parameter.
+#[derive(PartialEq, Eq, Hash, Copy, Clone)]
+pub struct Extension {
+    pub opcode: IntcodeMemoryCellType,
+    pub mnemonic: &'static str,
+    pub inputs: usize,
+}
+
+impl fmt::Debug for Extension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.mnemonic)
+    }
 }
 
-impl From<IntcodeMemoryCellType> for Operation {
-    fn from(code: IntcodeMemoryCellType) -> Self {
+impl TryFrom<IntcodeMemoryCellType> for Operation {
+    type Error = DecodeError;
+
+    fn try_from(code: IntcodeMemoryCellType) -> Result<Self, Self::Error> {
         match code {
-            1 => Self::Add,
-            2 => Self::Multiply,
-            3 => Self::Input,
-            4 => Self::Output,
-            5 => Self::JumpIfTrue,
-            6 => Self::JumpIfFalse,
-            7 => Self::LessThan,
-            8 => Self::Equals,
-            9 => Self::AdjustRelativeBase,
-            99 => Self::Halt,
-            x => panic!("Unknown opcode: {}", x),
+            1 => Ok(Self::Add),
+            2 => Ok(Self::Multiply),
+            3 => Ok(Self::Input),
+            4 => Ok(Self::Output),
+            5 => Ok(Self::JumpIfTrue),
+            6 => Ok(Self::JumpIfFalse),
+            7 => Ok(Self::LessThan),
+            8 => Ok(Self::Equals),
+            9 => Ok(Self::AdjustRelativeBase),
+            99 => Ok(Self::Halt),
+            x => Err(DecodeError::UnknownOpcode(x)),
         }
     }
 }
@@ -46,6 +73,7 @@ impl Into<IntcodeMemoryCellType> for Operation {
             Self::Equals => 8,
             Self::AdjustRelativeBase => 9,
             Self::Halt => 99,
+            Self::Extension(extension) => extension.opcode,
         }
     }
 }
@@ -63,6 +91,16 @@ impl Operation {
             Self::Equals => 3,
             Self::AdjustRelativeBase => 1,
             Self::Halt => 0,
+            Self::Extension(extension) => extension.inputs + 1,
+        }
+    }
+
+    pub fn write_parameter(&self) -> Option<usize> {
+        match *self {
+            Self::Add | Self::Multiply | Self::LessThan | Self::Equals => Some(2),
+            Self::Input => Some(0),
+            Self::Extension(extension) => Some(extension.inputs),
+            _ => None,
         }
     }
 }
@@ -73,21 +111,36 @@ pub struct OpCode {
     pub parameter_modes: Vec<ParameterMode>,
 }
 
-impl From<IntcodeMemoryCellType> for OpCode {
-    fn from(opcode: IntcodeMemoryCellType) -> Self {
+impl OpCode {
+    /// Decodes `opcode` using the operations of `instruction_set`.
+    pub fn decode<C, S: InstructionSet<C> + ?Sized>(
+        opcode: IntcodeMemoryCellType,
+        instruction_set: &S,
+    ) -> Result<OpCode, DecodeError> {
         let operation_int = opcode % 100;
-        let operation = Operation::from(operation_int);
+        let operation = instruction_set
+            .operation(operation_int)
+            .filter(|operation| operation.parameter_count() <= MAX_EXTENSION_INPUTS + 1)
+            .ok_or(DecodeError::UnknownOpcode(operation_int))?;
         let mut parameter_modes = Vec::new();
         let mut parameter_section = opcode / 100;
         for _ in 0..operation.parameter_count() {
             let mode = parameter_section % 10;
-            parameter_modes.push(ParameterMode::from(mode));
+            parameter_modes.push(ParameterMode::try_from(mode)?);
             parameter_section /= 10;
         }
-        OpCode {
-            operation: operation,
-            parameter_modes: parameter_modes,
-        }
+        Ok(OpCode {
+            operation,
+            parameter_modes,
+        })
+    }
+}
+
+impl TryFrom<IntcodeMemoryCellType> for OpCode {
+    type Error = DecodeError;
+
+    fn try_from(opcode: IntcodeMemoryCellType) -> Result<Self, Self::Error> {
+        OpCode::decode::<IntcodeMemoryCellType, _>(opcode, &StandardIsa)
     }
 }
 
@@ -114,33 +167,43 @@ mod tests {
     #[test]
     fn test_opcode_from_int() {
         assert_eq!(
-            OpCode::from(1002),
-            OpCode {
+            OpCode::try_from(1002),
+            Ok(OpCode {
                 operation: Operation::Multiply,
                 parameter_modes: vec![
                     ParameterMode::Pointer,
                     ParameterMode::Value,
                     ParameterMode::Pointer
                 ]
-            }
+            })
         );
         assert_eq!(
-            OpCode::from(11001),
-            OpCode {
+            OpCode::try_from(11001),
+            Ok(OpCode {
                 operation: Operation::Add,
                 parameter_modes: vec![
                     ParameterMode::Pointer,
                     ParameterMode::Value,
                     ParameterMode::Value
                 ]
-            }
+            })
         );
         assert_eq!(
-            OpCode::from(99),
-            OpCode {
+            OpCode::try_from(99),
+            Ok(OpCode {
                 operation: Operation::Halt,
                 parameter_modes: vec![]
-            }
+            })
+        );
+    }
+
+    #[test]
+    fn test_opcode_from_invalid_int() {
+        assert_eq!(OpCode::try_from(42), Err(DecodeError::UnknownOpcode(42)));
+        assert_eq!(OpCode::try_from(-1), Err(DecodeError::UnknownOpcode(-1)));
+        assert_eq!(
+            OpCode::try_from(1301),
+            Err(DecodeError::UnknownParameterMode(3))
         );
     }
 
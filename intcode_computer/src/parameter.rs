@@ -1,10 +1,13 @@
+use super::cell::Cell;
+use super::operations::DecodeError;
 use super::IntcodeMemoryCellType;
+use std::convert::TryFrom;
 
 #[derive(Clone, Copy, Debug)]
-pub enum Parameter {
-    Value(IntcodeMemoryCellType),
+pub enum Parameter<C = IntcodeMemoryCellType> {
+    Value(C),
     Pointer(usize),
-    Relative(IntcodeMemoryCellType),
+    Relative(C),
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -14,7 +17,7 @@ pub enum ParameterMode {
     Relative,
 }
 
-impl Into<ParameterMode> for Parameter {
+impl<C> Into<ParameterMode> for Parameter<C> {
     fn into(self) -> ParameterMode {
         match self {
             Self::Value(_) => ParameterMode::Value,
@@ -24,23 +27,25 @@ impl Into<ParameterMode> for Parameter {
     }
 }
 
-impl Parameter {
-    pub fn raw_value(self) -> IntcodeMemoryCellType {
+impl<C: Cell> Parameter<C> {
+    pub fn raw_value(self) -> C {
         match self {
             Self::Value(value) => value,
-            Self::Pointer(addr) => addr as IntcodeMemoryCellType,
+            Self::Pointer(addr) => C::from(addr as i64),
             Self::Relative(offset) => offset,
         }
     }
 }
 
-impl From<IntcodeMemoryCellType> for ParameterMode {
-    fn from(code: IntcodeMemoryCellType) -> Self {
+impl TryFrom<IntcodeMemoryCellType> for ParameterMode {
+    type Error = DecodeError;
+
+    fn try_from(code: IntcodeMemoryCellType) -> Result<Self, Self::Error> {
         match code {
-            0 => Self::Pointer,
-            1 => Self::Value,
-            2 => Self::Relative,
-            x => panic!("Unknown parameter mode: {}", x),
+            0 => Ok(Self::Pointer),
+            1 => Ok(Self::Value),
+            2 => Ok(Self::Relative),
+            x => Err(DecodeError::UnknownParameterMode(x)),
         }
     }
 }
@@ -1,3 +1,4 @@
 pub use crate::{
-    execute, Computer, IntCodeComputer, IntcodeMemoryCellType, IntcodeMemoryType, Interrupt,
+    execute, Computer, IntCodeComputer, IntcodeError, IntcodeMemoryCellType, IntcodeMemoryType,
+    Interrupt,
 };
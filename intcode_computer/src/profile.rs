@@ -0,0 +1,142 @@
+use super::cell::Cell;
+use super::operations::Operation;
+use super::trace::{TraceEvent, Tracer};
+use super::Interrupt;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile {
+    pub steps: u64,
+    pub by_address: BTreeMap<usize, u64>,
+    pub by_operation: HashMap<Operation, u64>,
+    // how often a jump went back to each target, i.e. how often each loop went around
+    pub back_edges: BTreeMap<usize, u64>,
+    pub input_waits: u64,
+    pub input_wait_time: Duration,
+}
+
+fn hottest<K: Copy + Ord>(counts: impl Iterator<Item = (K, u64)>, count: usize) -> Vec<(K, u64)> {
+    let mut counts: Vec<_> = counts.collect();
+    counts.sort_by(|(a_key, a), (b_key, b)| b.cmp(a).then(a_key.cmp(b_key)));
+    counts.truncate(count);
+    counts
+}
+
+impl Profile {
+    pub fn hottest_addresses(&self, count: usize) -> Vec<(usize, u64)> {
+        hottest(self.by_address.iter().map(|(a, n)| (*a, *n)), count)
+    }
+
+    pub fn hottest_loops(&self, count: usize) -> Vec<(usize, u64)> {
+        hottest(self.back_edges.iter().map(|(a, n)| (*a, *n)), count)
+    }
+
+    pub fn operations(&self) -> Vec<(Operation, u64)> {
+        let mut operations: Vec<_> = self.by_operation.iter().map(|(o, n)| (*o, *n)).collect();
+        operations.sort_by(|(a_operation, a), (b_operation, b)| {
+            b.cmp(a)
+                .then_with(|| format!("{:?}", a_operation).cmp(&format!("{:?}", b_operation)))
+        });
+        operations
+    }
+}
+
+/// A tracer that builds up a `Profile` of everything the computer executes.
+#[derive(Debug, Default)]
+pub struct Profiler {
+    profile: Profile,
+    waiting_since: Option<Instant>,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler::default()
+    }
+
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    pub fn into_profile(self) -> Profile {
+        self.profile
+    }
+}
+
+fn jump_taken<C: Cell>(event: &TraceEvent<C>) -> Option<usize> {
+    let taken = match (event.operation, event.operands.first()) {
+        (Operation::JumpIfTrue, Some(condition)) => *condition != C::default(),
+        (Operation::JumpIfFalse, Some(condition)) => *condition == C::default(),
+        _ => false,
+    };
+    match event.operands.get(1).and_then(Cell::to_i64) {
+        Some(target) if taken && target >= 0 => Some(target as usize),
+        _ => None,
+    }
+}
+
+impl<C: Cell> Tracer<C> for Profiler {
+    fn record(&mut self, event: &TraceEvent<C>) {
+        if let Some(since) = self.waiting_since.take() {
+            self.profile.input_wait_time += since.elapsed();
+        }
+        self.profile.steps += 1;
+        *self
+            .profile
+            .by_address
+            .entry(event.instruction_ptr)
+            .or_default() += 1;
+        *self
+            .profile
+            .by_operation
+            .entry(event.operation)
+            .or_default() += 1;
+        if let Some(target) = jump_taken(event) {
+            if target <= event.instruction_ptr {
+                *self.profile.back_edges.entry(target).or_default() += 1;
+            }
+        }
+    }
+
+    fn interrupted(&mut self, interrupt: Interrupt) {
+        if interrupt == Interrupt::Input && self.waiting_since.is_none() {
+            self.profile.input_waits += 1;
+            self.waiting_since = Some(Instant::now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Computer, IntCodeComputer};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_profile() {
+        // counts memory[10] down from 3, then reads a value into it
+        let program = vec![1001, 10, -1, 10, 1005, 10, 0, 3, 10, 99, 3];
+        let profiler = Arc::new(Mutex::new(Profiler::new()));
+        let mut computer = IntCodeComputer::new(program);
+        computer.set_tracer(Box::new(profiler.clone()));
+        assert_eq!(computer.execute(), Ok(Interrupt::Input));
+        assert_eq!(computer.execute(), Ok(Interrupt::Input));
+        computer.provide_input(1);
+        computer.execute().unwrap();
+
+        let profile = profiler.lock().unwrap().profile().clone();
+        assert_eq!(profile.steps, 8);
+        assert_eq!(profile.hottest_addresses(2), vec![(0, 3), (4, 3)]);
+        assert_eq!(profile.hottest_loops(5), vec![(0, 2)]);
+        assert_eq!(
+            profile.operations(),
+            vec![
+                (Operation::Add, 3),
+                (Operation::JumpIfTrue, 3),
+                (Operation::Halt, 1),
+                (Operation::Input, 1)
+            ]
+        );
+        assert_eq!(profile.input_waits, 1);
+    }
+}
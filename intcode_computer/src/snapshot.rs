@@ -0,0 +1,153 @@
+use super::memory::{DenseMemory, Memory};
+use super::Interrupt;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+const HEADER: &str = "intcode snapshot";
+
+/// Everything needed to put a computer back exactly where it was.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot<M: Memory = DenseMemory> {
+    pub memory: M,
+    pub instruction_ptr: usize,
+    pub relative_base: M::Cell,
+    pub input_buffer: Vec<M::Cell>,
+    pub output_buffer: Vec<M::Cell>,
+    pub interrupted: Option<Interrupt>,
+}
+
+fn join<C: fmt::Display>(values: &[C]) -> String {
+    let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+    values.join(",")
+}
+
+fn parse_cells<C: FromStr>(values: Option<&str>) -> Result<Vec<C>, String> {
+    match values {
+        None => Ok(Vec::new()),
+        Some(values) => values
+            .split(',')
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| format!("invalid value '{}'", value))
+            })
+            .collect(),
+    }
+}
+
+fn parse_addressed_cells<M: Memory>(memory: &mut M, values: Option<&str>) -> Result<(), String> {
+    for cell in values.into_iter().flat_map(|values| values.split(',')) {
+        let mut parts = cell.splitn(2, ':');
+        let address = parts.next().and_then(|address| address.parse().ok());
+        let value = parts.next().and_then(|value| value.parse().ok());
+        match (address, value) {
+            (Some(address), Some(value)) => memory
+                .write(address, value)
+                .map_err(|error| error.to_string())?,
+            _ => return Err(format!("invalid cell '{}'", cell)),
+        }
+    }
+    Ok(())
+}
+
+fn interrupt_name(interrupt: Option<Interrupt>) -> &'static str {
+    match interrupt {
+        None => "none",
+        Some(Interrupt::Input) => "input",
+        Some(Interrupt::Output) => "output",
+        Some(Interrupt::Halt) => "halt",
+    }
+}
+
+impl<M: Memory> Snapshot<M> {
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+}
+
+impl<M: Memory + From<Vec<M::Cell>>> Snapshot<M> {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Snapshot<M>> {
+        fs::read_to_string(path)?
+            .parse()
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+}
+
+impl<M: Memory> fmt::Display for Snapshot<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        writeln!(f, "ip {}", self.instruction_ptr)?;
+        writeln!(f, "relative_base {}", self.relative_base)?;
+        writeln!(f, "interrupted {}", interrupt_name(self.interrupted))?;
+        writeln!(f, "input {}", join(&self.input_buffer))?;
+        writeln!(f, "output {}", join(&self.output_buffer))?;
+        match self.memory.to_vec() {
+            Some(cells) => writeln!(f, "memory {}", join(&cells)),
+            // too big to write out in full, so only the cells that are stored, as address:value
+            None => {
+                let cells: Vec<String> = self
+                    .memory
+                    .cells()
+                    .iter()
+                    .map(|(address, value)| format!("{}:{}", address, value))
+                    .collect();
+                writeln!(f, "cells {}", cells.join(","))
+            }
+        }
+    }
+}
+
+impl<M: Memory + From<Vec<M::Cell>>> FromStr for Snapshot<M> {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Snapshot<M>, String> {
+        let mut lines = source.lines().enumerate();
+        match lines.next() {
+            Some((_, HEADER)) => {}
+            _ => return Err("not an intcode snapshot".to_string()),
+        }
+        let mut snapshot = Snapshot {
+            memory: M::from(Vec::new()),
+            instruction_ptr: 0,
+            relative_base: M::Cell::default(),
+            input_buffer: Vec::new(),
+            output_buffer: Vec::new(),
+            interrupted: None,
+        };
+        for (index, line) in lines {
+            let mut fields = line.split_whitespace();
+            let (key, value) = (fields.next(), fields.next());
+            let parsed = match key {
+                None => Ok(()),
+                Some("ip") => value
+                    .and_then(|value| value.parse().ok())
+                    .map(|ip| snapshot.instruction_ptr = ip)
+                    .ok_or_else(|| "invalid instruction pointer".to_string()),
+                Some("relative_base") => value
+                    .and_then(|value| value.parse().ok())
+                    .map(|base| snapshot.relative_base = base)
+                    .ok_or_else(|| "invalid relative base".to_string()),
+                Some("interrupted") => {
+                    let interrupted = match value {
+                        Some("none") => Ok(None),
+                        Some("input") => Ok(Some(Interrupt::Input)),
+                        Some("output") => Ok(Some(Interrupt::Output)),
+                        Some("halt") => Ok(Some(Interrupt::Halt)),
+                        _ => Err("invalid interrupt".to_string()),
+                    };
+                    interrupted.map(|interrupted| snapshot.interrupted = interrupted)
+                }
+                Some("input") => parse_cells(value).map(|cells| snapshot.input_buffer = cells),
+                Some("output") => parse_cells(value).map(|cells| snapshot.output_buffer = cells),
+                Some("memory") => parse_cells(value).map(|cells| snapshot.memory = M::from(cells)),
+                Some("cells") => parse_addressed_cells(&mut snapshot.memory, value),
+                Some(key) => Err(format!("unexpected '{}'", key)),
+            };
+            parsed.map_err(|error| format!("line {}: {}", index + 1, error))?;
+        }
+        Ok(snapshot)
+    }
+}
@@ -3,7 +3,7 @@ macro_rules! execute {
     ($computer:expr, input $input:block, output $output:block) => {
         loop {
             let interrupt = $computer.execute();
-            match interrupt {
+            match interrupt.unwrap_or_else(|error| panic!("{}", error)) {
                 Interrupt::Input => $input,
                 Interrupt::Output => $output,
                 Interrupt::Halt => break,
@@ -13,7 +13,7 @@ macro_rules! execute {
     ($computer:expr, output $output:block, input $input:block) => {
         loop {
             let interrupt = $computer.execute();
-            match interrupt {
+            match interrupt.unwrap_or_else(|error| panic!("{}", error)) {
                 Interrupt::Input => $input,
                 Interrupt::Output => $output,
                 Interrupt::Halt => break,
@@ -23,7 +23,7 @@ macro_rules! execute {
     ($computer:expr, input $input:block) => {
         loop {
             let interrupt = $computer.execute();
-            match interrupt {
+            match interrupt.unwrap_or_else(|error| panic!("{}", error)) {
                 Interrupt::Input => $input,
                 Interrupt::Output => panic!("unexpected output interrupt"),
                 Interrupt::Halt => break,
@@ -33,7 +33,7 @@ macro_rules! execute {
     ($computer:expr, output $output:block) => {
         loop {
             let interrupt = $computer.execute();
-            match interrupt {
+            match interrupt.unwrap_or_else(|error| panic!("{}", error)) {
                 Interrupt::Input => panic!("unexpected input interrupt"),
                 Interrupt::Output => $output,
                 Interrupt::Halt => break,
@@ -43,7 +43,7 @@ macro_rules! execute {
     ($computer:expr) => {
         loop {
             let interrupt = $computer.execute();
-            match interrupt {
+            match interrupt.unwrap_or_else(|error| panic!("{}", error)) {
                 Interrupt::Input => panic!("unexpected input interrupt"),
                 Interrupt::Output => panic!("unexpected output interrupt"),
                 Interrupt::Halt => break,
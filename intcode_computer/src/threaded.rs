@@ -0,0 +1,300 @@
+use super::{Computer, IntCodeComputer, IntcodeError, IntcodeMemoryCellType, Interrupt};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+
+/// Every pipe made by the same runner shares one of these, so a machine waiting on any of them
+/// can tell whether the whole network is stuck.
+#[derive(Debug, Default)]
+struct Shared {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    buffers: Vec<VecDeque<IntcodeMemoryCellType>>,
+    // machines still executing, `None` until a runner starts them
+    running: Option<usize>,
+    // the buffer each waiting machine is blocked on
+    waiting_on: Vec<usize>,
+    deadlocked: bool,
+}
+
+impl State {
+    fn check_deadlock(&mut self) {
+        let all_waiting =
+            self.running == Some(self.waiting_on.len()) && !self.waiting_on.is_empty();
+        if all_waiting
+            && self
+                .waiting_on
+                .iter()
+                .all(|index| self.buffers[*index].is_empty())
+        {
+            self.deadlocked = true;
+        }
+    }
+
+    fn finished(&self) -> bool {
+        self.deadlocked || self.running == Some(0)
+    }
+}
+
+/// A pipe that can be shared between threads, where `receive` blocks until a value is sent.
+#[derive(Debug, Clone)]
+pub struct SyncPipe {
+    shared: Arc<Shared>,
+    index: usize,
+}
+
+impl SyncPipe {
+    pub fn new() -> SyncPipe {
+        SyncPipe::with_shared(Arc::default())
+    }
+
+    fn with_shared(shared: Arc<Shared>) -> SyncPipe {
+        let index = {
+            let mut state = shared.state.lock().unwrap();
+            state.buffers.push(VecDeque::new());
+            state.buffers.len() - 1
+        };
+        SyncPipe { shared, index }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.shared.state.lock().unwrap()
+    }
+
+    pub fn send(&self, value: IntcodeMemoryCellType) {
+        self.lock().buffers[self.index].push_back(value);
+        self.shared.changed.notify_all();
+    }
+
+    /// Waits for a value. For a pipe made by a `ThreadedRunner` this gives up with `None` once
+    /// its machines have all stopped or deadlocked, as nothing could ever arrive.
+    pub fn receive(&self) -> Option<IntcodeMemoryCellType> {
+        let mut state = self.lock();
+        loop {
+            if let Some(value) = state.buffers[self.index].pop_front() {
+                return Some(value);
+            }
+            if state.finished() {
+                return None;
+            }
+            state = self.shared.changed.wait(state).unwrap();
+        }
+    }
+
+    /// Like `receive`, but counts the caller as a blocked machine, declaring a deadlock if every
+    /// other running machine is blocked too.
+    fn receive_as_machine(&self) -> Option<IntcodeMemoryCellType> {
+        let mut state = self.lock();
+        state.waiting_on.push(self.index);
+        state.check_deadlock();
+        self.shared.changed.notify_all();
+        let value = loop {
+            if let Some(value) = state.buffers[self.index].pop_front() {
+                break Some(value);
+            }
+            if state.deadlocked {
+                break None;
+            }
+            state = self.shared.changed.wait(state).unwrap();
+        };
+        let position = state
+            .waiting_on
+            .iter()
+            .position(|index| *index == self.index);
+        state.waiting_on.swap_remove(position.unwrap());
+        value
+    }
+
+    pub fn try_receive(&self) -> Option<IntcodeMemoryCellType> {
+        self.lock().buffers[self.index].pop_front()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().buffers[self.index].is_empty()
+    }
+}
+
+impl Default for SyncPipe {
+    fn default() -> Self {
+        SyncPipe::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MachineError {
+    Fault(IntcodeError),
+    /// Every machine was waiting for input that nothing was left to send.
+    Deadlock {
+        instruction_ptr: usize,
+    },
+}
+
+impl fmt::Display for MachineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fault(error) => write!(f, "{}", error),
+            Self::Deadlock { instruction_ptr } => {
+                write!(
+                    f,
+                    "deadlocked waiting for input at address {}",
+                    instruction_ptr
+                )
+            }
+        }
+    }
+}
+
+impl Error for MachineError {}
+
+impl From<IntcodeError> for MachineError {
+    fn from(error: IntcodeError) -> Self {
+        Self::Fault(error)
+    }
+}
+
+struct Machine {
+    computer: IntCodeComputer,
+    input: SyncPipe,
+    output: SyncPipe,
+}
+
+/// Runs computers on a thread each, connected by pipes made with `pipe`.
+///
+/// Once `run` starts, only the machines can feed the runner's pipes, so any input from outside
+/// has to be sent beforehand. That is what lets the runner call it a deadlock when every machine
+/// is waiting on an empty pipe.
+#[derive(Default)]
+pub struct ThreadedRunner {
+    shared: Arc<Shared>,
+    machines: Vec<Machine>,
+}
+
+impl ThreadedRunner {
+    pub fn new() -> ThreadedRunner {
+        ThreadedRunner::default()
+    }
+
+    pub fn pipe(&self) -> SyncPipe {
+        SyncPipe::with_shared(self.shared.clone())
+    }
+
+    /// Adds a machine that reads from `input` and writes to `output`, both of which must come
+    /// from this runner's `pipe`.
+    pub fn add(&mut self, computer: IntCodeComputer, input: &SyncPipe, output: &SyncPipe) {
+        assert!(
+            Arc::ptr_eq(&self.shared, &input.shared) && Arc::ptr_eq(&self.shared, &output.shared),
+            "pipes must come from the same runner"
+        );
+        self.machines.push(Machine {
+            computer,
+            input: input.clone(),
+            output: output.clone(),
+        });
+    }
+
+    /// Runs every machine until it halts, faults or deadlocks, returning them in the order they
+    /// were added.
+    pub fn run(self) -> Vec<Result<IntCodeComputer, MachineError>> {
+        let ThreadedRunner { shared, machines } = self;
+        shared.state.lock().unwrap().running = Some(machines.len());
+        let handles: Vec<_> = machines
+            .into_iter()
+            .map(|machine| {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    let result = run_machine(machine);
+                    let mut state = shared.state.lock().unwrap();
+                    *state.running.as_mut().unwrap() -= 1;
+                    state.check_deadlock();
+                    shared.changed.notify_all();
+                    result
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("machine thread panicked"))
+            .collect()
+    }
+}
+
+fn run_machine(machine: Machine) -> Result<IntCodeComputer, MachineError> {
+    let Machine {
+        mut computer,
+        input,
+        output,
+    } = machine;
+    loop {
+        match computer.execute()? {
+            Interrupt::Input => match input.receive_as_machine() {
+                Some(value) => computer.provide_input(value),
+                None => {
+                    return Err(MachineError::Deadlock {
+                        instruction_ptr: computer.instruction_ptr(),
+                    })
+                }
+            },
+            Interrupt::Output => output.send(computer.take_output()),
+            Interrupt::Halt => return Ok(computer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feedback_loop() {
+        // the day 7 feedback loop example
+        let program = vec![
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+        let mut runner = ThreadedRunner::new();
+        let pipes: Vec<_> = (0..5).map(|_| runner.pipe()).collect();
+        for (index, phase) in [9, 8, 7, 6, 5].iter().enumerate() {
+            pipes[index].send(*phase);
+            let output = &pipes[(index + 1) % pipes.len()];
+            runner.add(IntCodeComputer::new(program.clone()), &pipes[index], output);
+        }
+        pipes[0].send(0);
+        let results = runner.run();
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(pipes[0].receive(), Some(139629729));
+        assert_eq!(pipes[0].receive(), None);
+    }
+
+    #[test]
+    fn test_deadlock() {
+        let mut runner = ThreadedRunner::new();
+        let (a, b) = (runner.pipe(), runner.pipe());
+        // each echoes one value from its input, so both wait forever
+        runner.add(IntCodeComputer::new(vec![3, 5, 4, 5, 99, 0]), &a, &b);
+        runner.add(IntCodeComputer::new(vec![3, 5, 4, 5, 99, 0]), &b, &a);
+        let results = runner.run();
+        assert_eq!(
+            results
+                .into_iter()
+                .map(|result| result.err())
+                .collect::<Vec<_>>(),
+            vec![Some(MachineError::Deadlock { instruction_ptr: 0 }); 2]
+        );
+    }
+
+    #[test]
+    fn test_pipe_between_threads() {
+        let pipe = SyncPipe::new();
+        let sender = pipe.clone();
+        let handle = thread::spawn(move || sender.send(42));
+        assert_eq!(pipe.receive(), Some(42));
+        handle.join().unwrap();
+        assert!(pipe.is_empty());
+    }
+}
@@ -0,0 +1,213 @@
+use super::operations::Operation;
+use super::{IntcodeMemoryCellType, Interrupt};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+/// Everything one executed instruction did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEvent<C = IntcodeMemoryCellType> {
+    pub step: u64,
+    pub instruction_ptr: usize,
+    pub operation: Operation,
+    // read operands resolved to their values, write operands to the address written
+    pub operands: Vec<C>,
+    pub writes: Vec<(usize, C)>,
+    // the new relative base, if the instruction changed it
+    pub relative_base: Option<C>,
+}
+
+impl<C: fmt::Display> fmt::Display for TraceEvent<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>8} {:>6}  {:?}",
+            self.step, self.instruction_ptr, self.operation
+        )?;
+        for operand in self.operands.iter() {
+            write!(f, " {}", operand)?;
+        }
+        for (address, value) in self.writes.iter() {
+            write!(f, "  [{}] <- {}", address, value)?;
+        }
+        if let Some(base) = &self.relative_base {
+            write!(f, "  base <- {}", base)?;
+        }
+        Ok(())
+    }
+}
+
+impl<C: fmt::Display> TraceEvent<C> {
+    pub fn to_json(&self) -> String {
+        let join = |values: Vec<String>| values.join(",");
+        let operands = join(self.operands.iter().map(|o| o.to_string()).collect());
+        let writes = join(
+            self.writes
+                .iter()
+                .map(|(address, value)| format!("[{},{}]", address, value))
+                .collect(),
+        );
+        let relative_base = match &self.relative_base {
+            Some(base) => base.to_string(),
+            None => "null".to_string(),
+        };
+        format!(
+            "{{\"step\":{},\"ip\":{},\"op\":\"{:?}\",\"operands\":[{}],\"writes\":[{}],\"relative_base\":{}}}",
+            self.step, self.instruction_ptr, self.operation, operands, writes, relative_base
+        )
+    }
+}
+
+pub trait Tracer<C = IntcodeMemoryCellType> {
+    fn record(&mut self, event: &TraceEvent<C>);
+
+    /// Called whenever the computer stops, including when it waits for input without executing.
+    fn interrupted(&mut self, _interrupt: Interrupt) {}
+}
+
+// lets the caller keep a handle on a tracer the computer owns
+impl<C, T: Tracer<C>> Tracer<C> for Arc<Mutex<T>> {
+    fn record(&mut self, event: &TraceEvent<C>) {
+        self.lock().unwrap().record(event);
+    }
+
+    fn interrupted(&mut self, interrupt: Interrupt) {
+        self.lock().unwrap().interrupted(interrupt);
+    }
+}
+
+/// Keeps the most recent `capacity` events.
+#[derive(Debug, Clone)]
+pub struct RingBuffer<C = IntcodeMemoryCellType> {
+    capacity: usize,
+    events: VecDeque<TraceEvent<C>>,
+}
+
+impl<C> RingBuffer<C> {
+    pub fn new(capacity: usize) -> RingBuffer<C> {
+        RingBuffer {
+            capacity,
+            events: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn events(&self) -> impl Iterator<Item = &TraceEvent<C>> {
+        self.events.iter()
+    }
+}
+
+impl<C: Clone> Tracer<C> for RingBuffer<C> {
+    fn record(&mut self, event: &TraceEvent<C>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event.clone());
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TraceFormat {
+    Log,
+    JsonLines,
+}
+
+/// Writes one line per event, keeping the first write error for `into_inner`.
+pub struct WriteTracer<W: Write> {
+    writer: W,
+    format: TraceFormat,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> WriteTracer<W> {
+    pub fn new(writer: W, format: TraceFormat) -> WriteTracer<W> {
+        WriteTracer {
+            writer,
+            format,
+            error: None,
+        }
+    }
+
+    pub fn into_inner(mut self) -> io::Result<W> {
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => self.writer.flush().map(|_| self.writer),
+        }
+    }
+}
+
+impl<C: fmt::Display, W: Write> Tracer<C> for WriteTracer<W> {
+    fn record(&mut self, event: &TraceEvent<C>) {
+        if self.error.is_some() {
+            return;
+        }
+        let result = match self.format {
+            TraceFormat::Log => writeln!(self.writer, "{}", event),
+            TraceFormat::JsonLines => writeln!(self.writer, "{}", event.to_json()),
+        };
+        self.error = result.err();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Computer, IntCodeComputer};
+
+    #[test]
+    fn test_trace_events() {
+        let buffer = Arc::new(Mutex::new(RingBuffer::new(2)));
+        let mut computer = IntCodeComputer::new(vec![1101, 2, 3, 7, 109, 5, 99, 0]);
+        computer.set_tracer(Box::new(buffer.clone()));
+        computer.execute().unwrap();
+        let buffer = buffer.lock().unwrap();
+        let events: Vec<_> = buffer.events().cloned().collect();
+        assert_eq!(
+            events,
+            vec![
+                TraceEvent {
+                    step: 2,
+                    instruction_ptr: 4,
+                    operation: Operation::AdjustRelativeBase,
+                    operands: vec![5],
+                    writes: vec![],
+                    relative_base: Some(5),
+                },
+                TraceEvent {
+                    step: 3,
+                    instruction_ptr: 6,
+                    operation: Operation::Halt,
+                    operands: vec![],
+                    writes: vec![],
+                    relative_base: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_tracer() {
+        let event = TraceEvent {
+            step: 1,
+            instruction_ptr: 0,
+            operation: Operation::Add,
+            operands: vec![2, 3, 7],
+            writes: vec![(7, 5)],
+            relative_base: None,
+        };
+        let mut log = WriteTracer::new(Vec::new(), TraceFormat::Log);
+        log.record(&event);
+        let log = String::from_utf8(log.into_inner().unwrap()).unwrap();
+        assert_eq!(log, "       1      0  Add 2 3 7  [7] <- 5\n");
+        let mut json = WriteTracer::new(Vec::new(), TraceFormat::JsonLines);
+        json.record(&event);
+        let json = String::from_utf8(json.into_inner().unwrap()).unwrap();
+        assert_eq!(
+            json,
+            "{\"step\":1,\"ip\":0,\"op\":\"Add\",\"operands\":[2,3,7],\"writes\":[[7,5]],\"relative_base\":null}\n"
+        );
+    }
+}
@@ -0,0 +1,33 @@
+use super::condition::Condition;
+use super::{IntcodeMemoryCellType, Interrupt};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// Stops execution when an instruction reads or writes `address`, and `condition` holds
+/// afterwards if there is one. The condition can look at the value read or written as `value`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub address: usize,
+    pub access: Access,
+    pub condition: Option<Condition>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit<C = IntcodeMemoryCellType> {
+    pub address: usize,
+    pub access: Access,
+    pub value: C,
+}
+
+/// Why `execute_until_break` returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop<C = IntcodeMemoryCellType> {
+    Interrupt(Interrupt),
+    /// The instruction pointer reached a breakpoint whose condition holds.
+    Breakpoint(usize),
+    Watchpoint(WatchHit<C>),
+}